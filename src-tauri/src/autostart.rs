@@ -0,0 +1,272 @@
+use serde::Serialize;
+#[cfg(unix)]
+use std::fs;
+#[cfg(unix)]
+use std::path::PathBuf;
+#[cfg(target_os = "windows")]
+use winreg::enums::HKEY_CURRENT_USER;
+#[cfg(target_os = "windows")]
+use winreg::RegKey;
+
+use crate::error::AppError;
+use crate::map_err;
+
+#[cfg(target_os = "windows")]
+const RUN_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Run";
+#[cfg(target_os = "windows")]
+const APP_NAME: &str = "FTPBrowser";
+#[cfg(target_os = "macos")]
+const AGENT_LABEL: &str = "com.ftpbrowser.app";
+
+/// What is actually registered to start at login, as opposed to the
+/// preference the frontend remembers.
+#[derive(Debug, Clone, Serialize)]
+pub struct AutostartStatus {
+  supported: bool,
+  enabled: bool,
+  /// Registry value, `.desktop` file or LaunchAgent plist.
+  location: Option<String>,
+  /// The command that will run, as read back from `location`.
+  command: Option<String>,
+  /// Whether `command` starts this executable, so a moved install can be
+  /// detected and re-registered.
+  current: bool,
+}
+
+/// The executable to register. AppImages run from a temporary mount, so the
+/// image itself is registered instead.
+fn app_executable() -> Result<String, AppError> {
+  if cfg!(target_os = "linux") {
+    if let Some(image) = std::env::var_os("APPIMAGE") {
+      return Ok(image.to_string_lossy().to_string());
+    }
+  }
+  let exe = std::env::current_exe().map_err(map_err)?;
+  Ok(exe.to_string_lossy().to_string())
+}
+
+#[cfg(unix)]
+fn home_dir() -> Result<PathBuf, AppError> {
+  std::env::var_os("HOME")
+    .map(PathBuf::from)
+    .ok_or_else(|| "HOME is not set".into())
+}
+
+#[cfg(target_os = "windows")]
+pub fn set_autostart(enable: bool) -> Result<(), AppError> {
+  let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+  let (key, _) = hkcu.create_subkey(RUN_KEY).map_err(map_err)?;
+  if enable {
+    key.set_value(APP_NAME, &app_executable()?).map_err(map_err)?;
+  } else {
+    let _ = key.delete_value(APP_NAME);
+  }
+  Ok(())
+}
+
+#[cfg(target_os = "windows")]
+pub fn autostart_status() -> Result<AutostartStatus, AppError> {
+  let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+  let command: Option<String> = hkcu
+    .open_subkey(RUN_KEY)
+    .ok()
+    .and_then(|key| key.get_value(APP_NAME).ok());
+  let current = command.as_deref() == Some(app_executable()?.as_str());
+  Ok(AutostartStatus {
+    supported: true,
+    enabled: command.is_some(),
+    location: Some(format!("HKEY_CURRENT_USER\\{}\\{}", RUN_KEY, APP_NAME)),
+    command,
+    current,
+  })
+}
+
+/// `$XDG_CONFIG_HOME/autostart/ender-transfer.desktop`, per the XDG
+/// autostart spec.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn desktop_file() -> Result<PathBuf, AppError> {
+  let config = match std::env::var_os("XDG_CONFIG_HOME") {
+    Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+    _ => home_dir()?.join(".config"),
+  };
+  Ok(config.join("autostart").join("ender-transfer.desktop"))
+}
+
+/// Quotes an `Exec` argument as the desktop entry spec requires.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn quote_exec(path: &str) -> String {
+  let mut quoted = String::from("\"");
+  for c in path.chars() {
+    if matches!(c, '"' | '`' | '$' | '\\') {
+      quoted.push('\\');
+    }
+    quoted.push(c);
+  }
+  quoted.push('"');
+  quoted
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn unquote_exec(exec: &str) -> String {
+  let inner = exec
+    .strip_prefix('"')
+    .and_then(|exec| exec.strip_suffix('"'))
+    .unwrap_or(exec);
+  let mut out = String::new();
+  let mut chars = inner.chars();
+  while let Some(c) = chars.next() {
+    if c == '\\' {
+      if let Some(next) = chars.next() {
+        out.push(next);
+      }
+    } else {
+      out.push(c);
+    }
+  }
+  out
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn set_autostart(enable: bool) -> Result<(), AppError> {
+  let path = desktop_file()?;
+  if !enable {
+    return match fs::remove_file(&path) {
+      Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(map_err(err)),
+      _ => Ok(()),
+    };
+  }
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent).map_err(map_err)?;
+  }
+  let entry = format!(
+    "[Desktop Entry]\nType=Application\nName=Ender Transfer\nExec={}\nTerminal=false\nX-GNOME-Autostart-enabled=true\n",
+    quote_exec(&app_executable()?)
+  );
+  fs::write(&path, entry).map_err(map_err)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn autostart_status() -> Result<AutostartStatus, AppError> {
+  let path = desktop_file()?;
+  let entry = fs::read_to_string(&path).ok();
+  let command = entry.as_deref().and_then(|entry| {
+    entry
+      .lines()
+      .find_map(|line| line.trim().strip_prefix("Exec="))
+      .map(unquote_exec)
+  });
+  // Session managers skip entries hidden or disabled through their settings.
+  let hidden = entry.as_deref().is_some_and(|entry| {
+    entry.lines().any(|line| {
+      let line = line.trim();
+      line == "Hidden=true" || line == "X-GNOME-Autostart-enabled=false"
+    })
+  });
+  let current = command.as_deref() == Some(app_executable()?.as_str());
+  Ok(AutostartStatus {
+    supported: true,
+    enabled: entry.is_some() && !hidden,
+    location: Some(path.to_string_lossy().to_string()),
+    command,
+    current,
+  })
+}
+
+#[cfg(target_os = "macos")]
+fn agent_plist() -> Result<PathBuf, AppError> {
+  Ok(
+    home_dir()?
+      .join("Library")
+      .join("LaunchAgents")
+      .join(format!("{}.plist", AGENT_LABEL)),
+  )
+}
+
+#[cfg(target_os = "macos")]
+fn xml_escape(value: &str) -> String {
+  value
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+#[cfg(target_os = "macos")]
+fn xml_unescape(value: &str) -> String {
+  value
+    .replace("&quot;", "\"")
+    .replace("&gt;", ">")
+    .replace("&lt;", "<")
+    .replace("&amp;", "&")
+}
+
+/// Writes a LaunchAgent that launchd runs at the next login. It is not
+/// loaded now, since `RunAtLoad` would start a second instance.
+#[cfg(target_os = "macos")]
+pub fn set_autostart(enable: bool) -> Result<(), AppError> {
+  let path = agent_plist()?;
+  if !enable {
+    return match fs::remove_file(&path) {
+      Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(map_err(err)),
+      _ => Ok(()),
+    };
+  }
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent).map_err(map_err)?;
+  }
+  let plist = format!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+<plist version=\"1.0\">\n\
+<dict>\n\
+  <key>Label</key>\n\
+  <string>{}</string>\n\
+  <key>ProgramArguments</key>\n\
+  <array>\n\
+    <string>{}</string>\n\
+  </array>\n\
+  <key>RunAtLoad</key>\n\
+  <true/>\n\
+</dict>\n\
+</plist>\n",
+    AGENT_LABEL,
+    xml_escape(&app_executable()?)
+  );
+  fs::write(&path, plist).map_err(map_err)
+}
+
+#[cfg(target_os = "macos")]
+pub fn autostart_status() -> Result<AutostartStatus, AppError> {
+  let path = agent_plist()?;
+  let plist = fs::read_to_string(&path).ok();
+  let command = plist.as_deref().and_then(|plist| {
+    let (_, rest) = plist.split_once("<key>ProgramArguments</key>")?;
+    let (_, rest) = rest.split_once("<string>")?;
+    let (value, _) = rest.split_once("</string>")?;
+    Some(xml_unescape(value))
+  });
+  let current = command.as_deref() == Some(app_executable()?.as_str());
+  Ok(AutostartStatus {
+    supported: true,
+    enabled: plist.is_some(),
+    location: Some(path.to_string_lossy().to_string()),
+    command,
+    current,
+  })
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+pub fn set_autostart(_enable: bool) -> Result<(), AppError> {
+  Err(AppError::new(crate::error::ErrorKind::Unsupported, "Autostart is not supported on this platform"))
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+pub fn autostart_status() -> Result<AutostartStatus, AppError> {
+  Ok(AutostartStatus {
+    supported: false,
+    enabled: false,
+    location: None,
+    command: None,
+    current: false,
+  })
+}
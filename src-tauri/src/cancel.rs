@@ -0,0 +1,80 @@
+use crate::error::{AppError, ErrorKind};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// The error a cancelled or paused transfer returns.
+pub fn cancelled() -> AppError {
+  AppError::new(ErrorKind::Cancelled, "Transfer cancelled")
+}
+
+/// Why a running transfer was asked to stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+  Cancel,
+  /// Stop but keep the partial file, so the queue can continue it later.
+  Pause,
+}
+
+/// Running transfers by id, with whether they were asked to stop. Copy loops
+/// poll the flag between chunks, so a transfer stops within one buffer.
+#[derive(Default)]
+pub struct Cancellations {
+  running: Mutex<HashMap<String, Option<Stop>>>,
+}
+
+impl Cancellations {
+  /// Registers a transfer. The queue registers its jobs before they start,
+  /// so a stop requested in between is kept.
+  pub fn begin(&self, id: &str) {
+    if let Ok(mut running) = self.running.lock() {
+      running.entry(id.to_string()).or_insert(None);
+    }
+  }
+
+  /// Returns whether a transfer with that id was running.
+  pub fn cancel(&self, id: &str) -> bool {
+    self.request(id, Stop::Cancel)
+  }
+
+  /// Like `cancel`, but a pending cancel is not downgraded.
+  pub fn pause(&self, id: &str) -> bool {
+    self.request(id, Stop::Pause)
+  }
+
+  fn request(&self, id: &str, stop: Stop) -> bool {
+    let mut running = match self.running.lock() {
+      Ok(running) => running,
+      Err(_) => return false,
+    };
+    match running.get_mut(id) {
+      Some(requested) => {
+        if *requested != Some(Stop::Cancel) {
+          *requested = Some(stop);
+        }
+        true
+      }
+      None => false,
+    }
+  }
+
+  pub fn is_cancelled(&self, id: &str) -> bool {
+    self.stop(id).is_some()
+  }
+
+  pub fn stop(&self, id: &str) -> Option<Stop> {
+    self
+      .running
+      .lock()
+      .ok()
+      .and_then(|running| running.get(id).copied().flatten())
+  }
+
+  /// Forgets the transfer and returns why it was stopped, if it was.
+  pub fn finish(&self, id: &str) -> Option<Stop> {
+    self
+      .running
+      .lock()
+      .ok()
+      .and_then(|mut running| running.remove(id).flatten())
+  }
+}
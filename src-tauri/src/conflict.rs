@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+use tauri::{Manager, Window};
+
+use crate::cancel::cancelled;
+use crate::error::AppError;
+use crate::queue::TransferDirection;
+use crate::settings::ConflictPolicy;
+use crate::{log_event, map_err, AppState};
+
+/// Unanswered prompts are skipped after this long so a closed dialog cannot
+/// hold a worker connection forever.
+const ASK_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileInfo {
+  pub path: String,
+  pub size: Option<u64>,
+  /// Milliseconds since the Unix epoch.
+  pub modified: Option<i64>,
+}
+
+/// A transfer whose target already exists.
+pub struct Conflict<'a> {
+  pub id: &'a str,
+  pub direction: TransferDirection,
+  pub source: FileInfo,
+  pub target: FileInfo,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ConflictPrompt {
+  id: String,
+  direction: TransferDirection,
+  source: FileInfo,
+  target: FileInfo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictDecision {
+  Overwrite,
+  Skip,
+  Rename,
+}
+
+/// Where a transfer should write, or `None` to leave the target alone.
+pub type Resolution = Option<String>;
+
+/// Transfers waiting on the user for a `transfer-conflict` prompt.
+#[derive(Default)]
+pub struct ConflictPrompts {
+  decisions: Mutex<HashMap<String, Option<ConflictDecision>>>,
+  answered: Condvar,
+}
+
+/// `name (1).ext`, `name (2).ext`, ... in the same directory.
+fn numbered_name(path: &str, number: usize) -> String {
+  let split = path.rfind(['/', '\\']).map(|index| index + 1).unwrap_or(0);
+  let (dir, name) = path.split_at(split);
+  match name.rfind('.').filter(|index| *index > 0) {
+    Some(dot) => format!("{}{} ({}){}", dir, &name[..dot], number, &name[dot..]),
+    None => format!("{}{} ({})", dir, name, number),
+  }
+}
+
+fn free_name(path: &str, exists: &mut dyn FnMut(&str) -> bool) -> Result<String, AppError> {
+  for number in 1..1000 {
+    let candidate = numbered_name(path, number);
+    if !exists(&candidate) {
+      return Ok(candidate);
+    }
+  }
+  Err(format!("No free name for {}", path).into())
+}
+
+/// Newer only when both times are known; otherwise falls back to comparing
+/// sizes, since many FTP listings carry no usable time.
+fn source_is_newer(source: &FileInfo, target: &FileInfo) -> bool {
+  match (source.modified, target.modified) {
+    (Some(source), Some(target)) => source > target,
+    _ => sizes_differ(source, target),
+  }
+}
+
+fn sizes_differ(source: &FileInfo, target: &FileInfo) -> bool {
+  match (source.size, target.size) {
+    (Some(source), Some(target)) => source != target,
+    _ => true,
+  }
+}
+
+impl ConflictPrompts {
+  fn ask(&self, window: &Window, conflict: &Conflict) -> Result<ConflictDecision, AppError> {
+    let id = conflict.id;
+    let mut decisions = self.decisions.lock().map_err(map_err)?;
+    decisions.insert(id.to_string(), None);
+    let _ = window.emit(
+      "transfer-conflict",
+      ConflictPrompt {
+        id: id.to_string(),
+        direction: conflict.direction,
+        source: conflict.source.clone(),
+        target: conflict.target.clone(),
+      },
+    );
+    let cancels = &window.state::<AppState>().cancels;
+    let (mut decisions, _) = self
+      .answered
+      .wait_timeout_while(decisions, ASK_TIMEOUT, |decisions| {
+        matches!(decisions.get(id), Some(None)) && !cancels.is_cancelled(id)
+      })
+      .map_err(map_err)?;
+    let decision = decisions.remove(id).flatten();
+    if cancels.is_cancelled(id) {
+      return Err(cancelled());
+    }
+    Ok(decision.unwrap_or(ConflictDecision::Skip))
+  }
+
+  /// Wakes transfers waiting on a prompt so a cancelled one stops waiting.
+  /// Call after the stop was requested.
+  pub fn wake(&self) {
+    // Taking the lock orders the wakeup after a waiter's last check.
+    let _decisions = self.decisions.lock();
+    self.answered.notify_all();
+  }
+
+  pub fn resolve(&self, id: &str, decision: ConflictDecision) -> Result<(), AppError> {
+    let mut decisions = self.decisions.lock().map_err(map_err)?;
+    match decisions.get_mut(id) {
+      Some(slot) => *slot = Some(decision),
+      None => return Err(format!("No pending conflict for {}", id).into()),
+    }
+    self.answered.notify_all();
+    Ok(())
+  }
+
+  /// Applies `policy` and returns the path to write to, or `None` to skip the
+  /// transfer. `exists` checks candidate names on the target side for the
+  /// rename policy.
+  pub fn settle(
+    &self,
+    window: &Window,
+    policy: ConflictPolicy,
+    conflict: &Conflict,
+    exists: &mut dyn FnMut(&str) -> bool,
+  ) -> Result<Resolution, AppError> {
+    let (source, target) = (&conflict.source, &conflict.target);
+    let decision = match policy {
+      ConflictPolicy::Overwrite => ConflictDecision::Overwrite,
+      ConflictPolicy::Skip => ConflictDecision::Skip,
+      ConflictPolicy::Rename => ConflictDecision::Rename,
+      ConflictPolicy::OverwriteIfNewer if source_is_newer(source, target) => ConflictDecision::Overwrite,
+      ConflictPolicy::OverwriteIfSizeDiffers if sizes_differ(source, target) => ConflictDecision::Overwrite,
+      ConflictPolicy::OverwriteIfNewer | ConflictPolicy::OverwriteIfSizeDiffers => ConflictDecision::Skip,
+      ConflictPolicy::Ask => self.ask(window, conflict)?,
+    };
+    match decision {
+      ConflictDecision::Overwrite => Ok(Some(target.path.clone())),
+      ConflictDecision::Skip => {
+        log_event(window, "info", format!("Skipped {}: target exists", target.path));
+        Ok(None)
+      }
+      ConflictDecision::Rename => {
+        let renamed = free_name(&target.path, exists)?;
+        let name = Path::new(&renamed)
+          .file_name()
+          .map(|name| name.to_string_lossy().to_string())
+          .unwrap_or_else(|| renamed.clone());
+        log_event(window, "info", format!("Saving as {}", name));
+        Ok(Some(renamed))
+      }
+    }
+  }
+}
@@ -0,0 +1,219 @@
+use serde::Serialize;
+use std::fmt;
+use std::io;
+use suppaftp::FtpError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+  NotConnected,
+  AuthFailed,
+  PermissionDenied,
+  NotFound,
+  Timeout,
+  TlsError,
+  /// The local disk is full.
+  DiskFull,
+  /// The server is out of space or over quota.
+  RemoteFull,
+  Cancelled,
+  /// A transferred file failed the integrity check.
+  VerifyMismatch,
+  /// A file is over the size limit for a preview.
+  TooLarge,
+  /// Not available on this operating system.
+  Unsupported,
+  Other,
+}
+
+/// The error commands and the code beneath them return. The kind is set
+/// where the error is raised, from the FTP reply, the OS error or the SSH
+/// status, so the frontend can offer a fix without matching on text.
+#[derive(Debug, Clone, Serialize)]
+pub struct AppError {
+  pub kind: ErrorKind,
+  /// FTP reply code, when the server sent one.
+  pub code: Option<u16>,
+  pub message: String,
+}
+
+impl AppError {
+  pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+    Self {
+      kind,
+      code: None,
+      message: message.into(),
+    }
+  }
+
+  /// An error for an FTP reply the command did not expect.
+  pub fn reply(code: u32, message: impl Into<String>) -> Self {
+    Self {
+      kind: kind_from_reply(code),
+      code: u16::try_from(code).ok(),
+      message: message.into(),
+    }
+  }
+}
+
+impl fmt::Display for AppError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.message)
+  }
+}
+
+impl std::error::Error for AppError {}
+
+/// Messages written by this app carry no kind of their own.
+impl From<String> for AppError {
+  fn from(message: String) -> Self {
+    Self::new(ErrorKind::Other, message)
+  }
+}
+
+impl From<&str> for AppError {
+  fn from(message: &str) -> Self {
+    Self::new(ErrorKind::Other, message)
+  }
+}
+
+/// ENOSPC and EDQUOT, or their Windows counterparts. `io::ErrorKind` only
+/// names these on newer compilers.
+fn is_disk_full(err: &io::Error) -> bool {
+  #[cfg(windows)]
+  const CODES: [i32; 2] = [39, 112];
+  #[cfg(target_os = "macos")]
+  const CODES: [i32; 2] = [28, 69];
+  #[cfg(not(any(windows, target_os = "macos")))]
+  const CODES: [i32; 2] = [28, 122];
+  err.raw_os_error().map(|code| CODES.contains(&code)).unwrap_or(false)
+}
+
+impl From<io::Error> for AppError {
+  fn from(err: io::Error) -> Self {
+    // Errors raised inside a copy loop, such as a cancel, travel through
+    // `io::Error` and keep their kind.
+    if let Some(inner) = err.get_ref().and_then(|inner| inner.downcast_ref::<AppError>()) {
+      return inner.clone();
+    }
+    let kind = if is_disk_full(&err) {
+      ErrorKind::DiskFull
+    } else {
+      match err.kind() {
+        io::ErrorKind::NotFound => ErrorKind::NotFound,
+        io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+        // Socket read timeouts surface as WouldBlock on Unix.
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => ErrorKind::Timeout,
+        io::ErrorKind::ConnectionRefused
+        | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::NotConnected
+        | io::ErrorKind::BrokenPipe
+        | io::ErrorKind::UnexpectedEof => ErrorKind::NotConnected,
+        _ => ErrorKind::Other,
+      }
+    };
+    Self::new(kind, err.to_string())
+  }
+}
+
+fn kind_from_reply(code: u32) -> ErrorKind {
+  match code {
+    421 | 425 | 426 => ErrorKind::NotConnected,
+    530 | 332 => ErrorKind::AuthFailed,
+    // Servers that insist on TLS refuse AUTH-less logins with 534.
+    522 | 534 => ErrorKind::TlsError,
+    452 | 552 => ErrorKind::RemoteFull,
+    532 | 553 => ErrorKind::PermissionDenied,
+    // 550 also covers refused access, but a missing file is far more common.
+    550 => ErrorKind::NotFound,
+    _ => ErrorKind::Other,
+  }
+}
+
+impl From<FtpError> for AppError {
+  fn from(err: FtpError) -> Self {
+    let message = err.to_string();
+    match err {
+      FtpError::ConnectionError(inner) => {
+        let mut error = AppError::from(inner);
+        error.message = message;
+        error
+      }
+      FtpError::SecureError(_) => Self::new(ErrorKind::TlsError, message),
+      FtpError::UnexpectedResponse(response) => Self::reply(response.status.code(), message),
+      _ => Self::new(ErrorKind::Other, message),
+    }
+  }
+}
+
+/// libssh2 session errors and SFTP status codes (`LIBSSH2_ERROR_*`,
+/// `LIBSSH2_FX_*`).
+impl From<ssh2::Error> for AppError {
+  fn from(err: ssh2::Error) -> Self {
+    let kind = match err.code() {
+      ssh2::ErrorCode::Session(-18 | -19 | -15) => ErrorKind::AuthFailed,
+      ssh2::ErrorCode::Session(-9 | -30) => ErrorKind::Timeout,
+      ssh2::ErrorCode::Session(-1 | -7 | -13 | -43) => ErrorKind::NotConnected,
+      ssh2::ErrorCode::SFTP(2 | 10) => ErrorKind::NotFound,
+      ssh2::ErrorCode::SFTP(3 | 12) => ErrorKind::PermissionDenied,
+      ssh2::ErrorCode::SFTP(14 | 15) => ErrorKind::RemoteFull,
+      ssh2::ErrorCode::SFTP(6 | 7) => ErrorKind::NotConnected,
+      _ => ErrorKind::Other,
+    };
+    Self::new(kind, err.to_string())
+  }
+}
+
+impl From<suppaftp::native_tls::Error> for AppError {
+  fn from(err: suppaftp::native_tls::Error) -> Self {
+    Self::new(ErrorKind::TlsError, err.to_string())
+  }
+}
+
+impl<S: fmt::Debug + 'static> From<suppaftp::native_tls::HandshakeError<S>> for AppError {
+  fn from(err: suppaftp::native_tls::HandshakeError<S>) -> Self {
+    Self::new(ErrorKind::TlsError, err.to_string())
+  }
+}
+
+impl<T> From<std::sync::PoisonError<T>> for AppError {
+  fn from(err: std::sync::PoisonError<T>) -> Self {
+    Self::new(ErrorKind::Other, err.to_string())
+  }
+}
+
+#[cfg(windows)]
+impl From<windows::core::Error> for AppError {
+  fn from(err: windows::core::Error) -> Self {
+    Self::new(ErrorKind::Other, err.message())
+  }
+}
+
+/// Errors that say nothing a user could act on beyond their message.
+macro_rules! other_errors {
+  ($($error:ty),* $(,)?) => {
+    $(
+      impl From<$error> for AppError {
+        fn from(err: $error) -> Self {
+          Self::new(ErrorKind::Other, err.to_string())
+        }
+      }
+    )*
+  };
+}
+
+other_errors!(
+  serde_json::Error,
+  tauri::Error,
+  image::ImageError,
+  base64::DecodeError,
+  std::string::FromUtf8Error,
+  keyring::Error,
+  aes_gcm::Error,
+  argon2::Error,
+  getrandom::Error,
+  sha2::digest::InvalidLength,
+  notify::Error,
+  roxmltree::Error,
+);
@@ -0,0 +1,645 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+
+use crate::{now_millis, FtpEntry};
+
+const MONTHS: [&str; 12] = [
+  "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// Whitespace-separated tokens with their byte offsets, so names can be cut
+/// from the original line with their inner spacing intact.
+fn split_tokens(line: &str) -> Vec<(usize, &str)> {
+  let mut tokens = Vec::new();
+  let mut start = None;
+  for (index, ch) in line.char_indices() {
+    if ch.is_whitespace() {
+      if let Some(begin) = start.take() {
+        tokens.push((begin, &line[begin..index]));
+      }
+    } else if start.is_none() {
+      start = Some(index);
+    }
+  }
+  if let Some(begin) = start {
+    tokens.push((begin, &line[begin..]));
+  }
+  tokens
+}
+
+fn rest_after<'a>(line: &'a str, token: (usize, &str)) -> &'a str {
+  &line[token.0 + token.1.len()..]
+}
+
+fn is_digits(value: &str) -> bool {
+  !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+fn month_number(value: &str) -> Option<u32> {
+  let lower = value.to_ascii_lowercase();
+  MONTHS
+    .iter()
+    .position(|month| *month == lower)
+    .map(|index| index as u32 + 1)
+}
+
+fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> Option<NaiveDateTime> {
+  NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(hour, minute, second)
+}
+
+fn millis(value: NaiveDateTime) -> i64 {
+  value.and_utc().timestamp_millis()
+}
+
+fn expand_year(year: i32) -> i32 {
+  match year {
+    0..=69 => 2000 + year,
+    70..=99 => 1900 + year,
+    _ => year,
+  }
+}
+
+/// Parses `HH:MM` or `HH:MM:SS`, with an optional AM/PM suffix.
+fn parse_time(value: &str) -> Option<(u32, u32, u32)> {
+  let upper = value.to_ascii_uppercase();
+  let (clock, meridiem) = if let Some(clock) = upper.strip_suffix("AM") {
+    (clock, Some(false))
+  } else if let Some(clock) = upper.strip_suffix("PM") {
+    (clock, Some(true))
+  } else {
+    (upper.as_str(), None)
+  };
+  let mut parts = clock.split(':');
+  let mut hour: u32 = parts.next()?.parse().ok()?;
+  let minute: u32 = parts.next()?.parse().ok()?;
+  let second: u32 = match parts.next() {
+    Some(value) => value.parse().ok()?,
+    None => 0,
+  };
+  if parts.next().is_some() || minute > 59 || second > 59 {
+    return None;
+  }
+  match meridiem {
+    Some(pm) if (1..=12).contains(&hour) => hour = hour % 12 + if pm { 12 } else { 0 },
+    Some(_) => return None,
+    None if hour > 23 => return None,
+    None => {}
+  }
+  Some((hour, minute, second))
+}
+
+/// `Mon DD HH:MM` or `Mon DD YYYY` as printed by `ls -l`. Without a year the
+/// date falls within the last year, so a future date means the year before.
+fn unix_date(month: &str, day: &str, time_or_year: &str, now: NaiveDateTime) -> Option<i64> {
+  let month = month_number(month)?;
+  let day: u32 = day.parse().ok()?;
+  if time_or_year.contains(':') {
+    let (hour, minute, _) = parse_time(time_or_year)?;
+    let year = now.year();
+    return at(year, month, day, hour, minute, 0)
+      .filter(|value| *value <= now + Duration::days(1))
+      .or_else(|| at(year - 1, month, day, hour, minute, 0))
+      .map(millis);
+  }
+  if time_or_year.len() != 4 {
+    return None;
+  }
+  at(time_or_year.parse().ok()?, month, day, 0, 0, 0).map(millis)
+}
+
+/// `YYYY-MM-DD` or `YYYY/MM/DD`.
+fn ymd(value: &str) -> Option<NaiveDate> {
+  let parts: Vec<&str> = value.split(['-', '/']).collect();
+  if parts.len() != 3 || parts[0].len() != 4 {
+    return None;
+  }
+  NaiveDate::from_ymd_opt(parts[0].parse().ok()?, parts[1].parse().ok()?, parts[2].parse().ok()?)
+}
+
+/// DOS-style `MM-DD-YY`, `MM/DD/YYYY` and similar. Day-first dates are
+/// recognized when the first field cannot be a month.
+fn mdy(value: &str) -> Option<NaiveDate> {
+  if let Some(date) = ymd(value) {
+    return Some(date);
+  }
+  let parts: Vec<&str> = value.split(['-', '/', '.']).collect();
+  if parts.len() != 3 {
+    return None;
+  }
+  let first: u32 = parts[0].parse().ok()?;
+  let second: u32 = parts[1].parse().ok()?;
+  let year = expand_year(parts[2].parse().ok()?);
+  let (month, day) = if first > 12 { (second, first) } else { (first, second) };
+  NaiveDate::from_ymd_opt(year, month, day)
+}
+
+/// VMS `DD-MON-YYYY`.
+fn vms_date(value: &str) -> Option<NaiveDate> {
+  let mut parts = value.split('-');
+  let day: u32 = parts.next()?.parse().ok()?;
+  let month = month_number(parts.next()?)?;
+  let year = expand_year(parts.next()?.parse().ok()?);
+  NaiveDate::from_ymd_opt(year, month, day)
+}
+
+fn date_time(date: NaiveDate, time: Option<&str>) -> Option<i64> {
+  let (hour, minute, second) = match time {
+    Some(time) => parse_time(time)?,
+    None => (0, 0, 0),
+  };
+  date.and_hms_opt(hour, minute, second).map(millis)
+}
+
+fn entry(name: &str, raw: &str) -> FtpEntry {
+  FtpEntry {
+    name: name.to_string(),
+    raw: Some(raw.to_string()),
+    ..Default::default()
+  }
+}
+
+/// Type character plus nine mode characters, optionally followed by an ACL
+/// or extended attribute marker.
+fn is_unix_permissions(value: &str) -> bool {
+  let bytes = value.as_bytes();
+  bytes.len() >= 10
+    && bytes.len() <= 11
+    && b"-dlcbpsD".contains(&bytes[0])
+    && bytes[1..10].iter().all(|byte| b"-rwxsStTlL?".contains(byte))
+    && bytes[10..].iter().all(|byte| b"+.@".contains(byte))
+}
+
+/// Finds the modification date in an `ls -l` style line. Returns the index of
+/// its first and last token and the parsed timestamp.
+fn find_unix_date(
+  tokens: &[(usize, &str)],
+  first: usize,
+  now: NaiveDateTime,
+) -> Option<(usize, usize, Option<i64>)> {
+  for index in first..tokens.len() {
+    if !is_digits(tokens[index - 1].1) {
+      continue;
+    }
+    // ls --time-style=long-iso: `2024-01-05 12:30`.
+    if let (Some(date), Some(time)) = (ymd(tokens[index].1), tokens.get(index + 1)) {
+      if index + 2 < tokens.len() && parse_time(time.1).is_some() {
+        return Some((index, index + 1, date_time(date, Some(time.1))));
+      }
+    }
+    if index + 3 < tokens.len()
+      && month_number(tokens[index].1).is_some()
+      && tokens[index + 1].1.parse::<u32>().map(|day| (1..=31).contains(&day)).unwrap_or(false)
+    {
+      let modified = unix_date(tokens[index].1, tokens[index + 1].1, tokens[index + 2].1, now);
+      if modified.is_some() {
+        return Some((index, index + 2, modified));
+      }
+    }
+  }
+  None
+}
+
+/// A day of the month, also in the `5.` form of German listings.
+fn is_day(value: &str) -> bool {
+  value
+    .trim_end_matches('.')
+    .parse::<u32>()
+    .map(|day| (1..=31).contains(&day))
+    .unwrap_or(false)
+}
+
+/// Falls back for dates in a form `find_unix_date` does not know, such as
+/// localized months (`Okt`, `5 mars`, `10月`): the date is taken to be the
+/// three tokens after the size, where a month word sits next to the day.
+fn guess_unix_date(tokens: &[(usize, &str)]) -> Option<(usize, usize, Option<i64>)> {
+  let is_word = |value: &str| !is_digits(value) && !is_day(value);
+  let candidates = 3..tokens.len().saturating_sub(3);
+  candidates
+    .clone()
+    .find(|&index| is_digits(tokens[index - 1].1) && is_word(tokens[index].1) && is_day(tokens[index + 1].1))
+    .or_else(|| {
+      candidates.clone().find(|&index| {
+        is_digits(tokens[index - 1].1) && is_day(tokens[index].1) && is_word(tokens[index + 1].1)
+      })
+    })
+    .map(|index| (index, index + 2, None))
+}
+
+/// Splits `name -> target` for symlinks and drops the `.`/`..` entries.
+fn finish_unix_entry(mut entry: FtpEntry, name: &str) -> Option<FtpEntry> {
+  let name = match name.split_once(" -> ") {
+    Some((link, target)) if entry.is_symlink => {
+      entry.link_target = Some(target.to_string());
+      link
+    }
+    _ => name,
+  };
+  if name.is_empty() || name == "." || name == ".." {
+    return None;
+  }
+  entry.name = name.to_string();
+  Some(entry)
+}
+
+fn parse_unix(line: &str, tokens: &[(usize, &str)], now: NaiveDateTime) -> Option<FtpEntry> {
+  let permissions = tokens[0].1;
+  let (date_start, date_end, modified) =
+    find_unix_date(tokens, 2, now).or_else(|| guess_unix_date(tokens))?;
+  let rest = rest_after(line, tokens[date_end]);
+  let name = rest.strip_prefix(' ').unwrap_or(rest);
+
+  // Between the mode and the size: link count, owner and group, each of which
+  // some servers leave out.
+  let mut middle: Vec<&str> = tokens[1..date_start - 1].iter().map(|token| token.1).collect();
+  if !middle.is_empty() && is_digits(middle[0]) {
+    middle.remove(0);
+  }
+
+  let mut entry = entry(name, line);
+  entry.is_dir = permissions.starts_with('d');
+  entry.is_symlink = permissions.starts_with('l');
+  entry.size = if entry.is_dir { None } else { tokens[date_start - 1].1.parse().ok() };
+  entry.modified = modified;
+  entry.permissions = Some(permissions.to_string());
+  entry.owner = middle.first().map(|value| value.to_string());
+  entry.group = middle.get(1).map(|value| value.to_string());
+  finish_unix_entry(entry, name)
+}
+
+/// `d [RWCEAFMS] owner  512 Jan 05 12:30 name`
+fn parse_netware(line: &str, tokens: &[(usize, &str)], now: NaiveDateTime) -> Option<FtpEntry> {
+  let (date_start, date_end, modified) = find_unix_date(tokens, 3, now)?;
+  let rest = rest_after(line, tokens[date_end]);
+  let name = rest.trim_start();
+  if name.is_empty() {
+    return None;
+  }
+  let mut entry = entry(name, line);
+  entry.is_dir = tokens[0].1 == "d";
+  entry.size = if entry.is_dir { None } else { tokens[date_start - 1].1.parse().ok() };
+  entry.modified = modified;
+  entry.permissions = Some(tokens[1].1.to_string());
+  if date_start > 3 {
+    entry.owner = Some(tokens[2].1.to_string());
+  }
+  Some(entry)
+}
+
+/// IIS and other DOS-style servers: `01-05-20  12:30PM  <DIR>  name`.
+fn parse_dos(line: &str, tokens: &[(usize, &str)]) -> Option<FtpEntry> {
+  let date = mdy(tokens[0].1)?;
+  let mut index = 1;
+  let mut time = tokens[1].1.to_string();
+  if let Some(meridiem) = tokens.get(2).filter(|token| {
+    token.1.eq_ignore_ascii_case("AM") || token.1.eq_ignore_ascii_case("PM")
+  }) {
+    time.push_str(meridiem.1);
+    index = 2;
+  }
+  parse_time(&time)?;
+  let kind = tokens.get(index + 1)?;
+  let is_dir = kind.1.eq_ignore_ascii_case("<DIR>");
+  let size = if is_dir {
+    None
+  } else {
+    Some(kind.1.replace(',', "").parse::<u64>().ok()?)
+  };
+  let name = rest_after(line, *kind).trim_start();
+  if name.is_empty() || name == "." || name == ".." {
+    return None;
+  }
+  let mut entry = entry(name, line);
+  entry.is_dir = is_dir;
+  entry.size = size;
+  entry.modified = date_time(date, Some(&time));
+  Some(entry)
+}
+
+fn is_vms_name(value: &str) -> bool {
+  match value.rsplit_once(';') {
+    Some((name, version)) => !name.is_empty() && is_digits(version),
+    None => false,
+  }
+}
+
+/// OpenVMS: `NAME.EXT;1  12/20  5-JAN-2020 12:30:45  [GROUP,OWNER]  (RWED,RWED,RE,)`.
+/// Sizes are given in 512-byte blocks.
+fn parse_vms(line: &str, tokens: &[(usize, &str)]) -> Option<FtpEntry> {
+  let (name, _) = tokens[0].1.rsplit_once(';')?;
+  let upper = name.to_ascii_uppercase();
+  let is_dir = upper.ends_with(".DIR");
+  let name = if is_dir { &name[..name.len() - 4] } else { name };
+  let mut entry = entry(name, line);
+  entry.is_dir = is_dir;
+  if !is_dir {
+    entry.size = tokens
+      .get(1)
+      .and_then(|token| token.1.split('/').next())
+      .and_then(|blocks| blocks.parse::<u64>().ok())
+      .map(|blocks| blocks * 512);
+  }
+  if let Some(date) = tokens.get(2).and_then(|token| vms_date(token.1)) {
+    let time = tokens.get(3).map(|token| token.1).filter(|value| parse_time(value).is_some());
+    entry.modified = date_time(date, time);
+  }
+  for (_, token) in &tokens[1..] {
+    if let Some(owner) = token.strip_prefix('[').and_then(|value| value.strip_suffix(']')) {
+      match owner.split_once(',') {
+        Some((group, owner)) => {
+          entry.group = Some(group.to_string());
+          entry.owner = Some(owner.to_string());
+        }
+        None => entry.owner = Some(owner.to_string()),
+      }
+    } else if token.starts_with('(') && token.ends_with(')') {
+      entry.permissions = Some(token.to_string());
+    }
+  }
+  Some(entry)
+}
+
+/// IBM i (AS/400): `OWNER  1234 05/01/20 12:30:00 *STMF  name`. Members of
+/// physical files are listed as `*MEM  LIB.LIB/FILE.FILE/MBR.MBR`.
+fn parse_as400(line: &str, tokens: &[(usize, &str)]) -> Option<FtpEntry> {
+  if tokens[0].1 == "*MEM" {
+    let path = rest_after(line, tokens[0]).trim();
+    let name = path.trim_end_matches('/').rsplit('/').next()?;
+    return Some(entry(name, line));
+  }
+  let kind = tokens[4].1;
+  let rest = rest_after(line, tokens[4]).trim();
+  let is_dir = matches!(kind, "*DIR" | "*LIB" | "*FLR") || rest.ends_with('/');
+  let path = rest.trim_end_matches('/');
+  let name = path.rsplit('/').next().unwrap_or(path);
+  if name.is_empty() || name == "." || name == ".." {
+    return None;
+  }
+  let mut entry = entry(name, line);
+  entry.is_dir = is_dir;
+  entry.size = if is_dir { None } else { tokens[1].1.parse().ok() };
+  entry.modified = mdy(tokens[2].1).and_then(|date| date_time(date, Some(tokens[3].1)));
+  entry.owner = Some(tokens[0].1.to_string());
+  entry.permissions = Some(kind.to_string());
+  Some(entry)
+}
+
+/// z/OS partitioned data set members:
+/// `NAME  01.03 2002/09/12 2002/09/12 14:56  22  22  0 USER1`.
+/// The size column counts records, not bytes, so it is not reported.
+fn parse_mvs_member(line: &str, tokens: &[(usize, &str)]) -> Option<FtpEntry> {
+  let changed = ymd(tokens[3].1)?;
+  let mut entry = entry(tokens[0].1, line);
+  entry.modified = date_time(changed, Some(tokens[4].1));
+  entry.owner = tokens.get(8).map(|token| token.1.to_string());
+  Some(entry)
+}
+
+/// z/OS data sets: `VOLUME 3390 2003/03/18 1 15 FB 80 3120 PO DATA.SET`.
+/// Partitioned data sets behave like directories.
+fn parse_mvs_dataset(line: &str, tokens: &[(usize, &str)]) -> Option<FtpEntry> {
+  let name = tokens.last()?.1;
+  let dsorg = tokens[tokens.len() - 2].1;
+  let mut entry = entry(name, line);
+  entry.is_dir = dsorg.starts_with("PO");
+  entry.modified = ymd(tokens[2].1).and_then(|date| date_time(date, None));
+  Some(entry)
+}
+
+/// Summary and column header lines that carry no entry.
+fn is_header(tokens: &[(usize, &str)]) -> bool {
+  let words: Vec<&str> = tokens.iter().map(|token| token.1).collect();
+  match words.as_slice() {
+    ["total", count] => is_digits(count),
+    ["Total", "of", ..] | ["Grand", "total", ..] => true,
+    ["Directory", path] => path.ends_with(']'),
+    ["Volume", "Unit", ..] | ["Name", "VV.MM", ..] => true,
+    _ => false,
+  }
+}
+
+fn parse_list_line(line: &str, now: NaiveDateTime) -> Option<FtpEntry> {
+  let line = line.trim_end_matches(['\r', '\n']);
+  let tokens = split_tokens(line);
+  if tokens.is_empty() || is_header(&tokens) {
+    return None;
+  }
+  let first = tokens[0].1;
+  let count = tokens.len();
+
+  // A recognized format is final, even when the line turns out to carry no
+  // entry such as `.` and `..`.
+  let parsed = if is_unix_permissions(first) && count >= 4 {
+    Some(parse_unix(line, &tokens, now))
+  } else if (first == "d" || first == "-") && count >= 7 && tokens[1].1.starts_with('[') {
+    Some(parse_netware(line, &tokens, now))
+  } else if count >= 4 && first.starts_with(|ch: char| ch.is_ascii_digit()) && tokens[1].1.contains(':') {
+    Some(parse_dos(line, &tokens))
+  } else if is_vms_name(first) {
+    Some(parse_vms(line, &tokens))
+  } else if (count >= 6 && tokens[4].1.starts_with('*') && is_digits(tokens[1].1))
+    || (first == "*MEM" && count >= 2)
+  {
+    Some(parse_as400(line, &tokens))
+  } else if count >= 8
+    && tokens[1].1.len() == 5
+    && tokens[1].1.as_bytes()[2] == b'.'
+    && ymd(tokens[2].1).is_some()
+  {
+    Some(parse_mvs_member(line, &tokens))
+  } else if count >= 10 && (ymd(tokens[2].1).is_some() || tokens[2].1 == "**NONE**") {
+    Some(parse_mvs_dataset(line, &tokens))
+  } else if first == "Migrated" && count == 2 {
+    Some(Some(entry(tokens[1].1, line)))
+  } else if first == "Pseudo" && count == 3 && tokens[1].1 == "Directory" {
+    let mut entry = entry(tokens[2].1, line);
+    entry.is_dir = true;
+    Some(Some(entry))
+  } else {
+    None
+  };
+  if let Some(parsed) = parsed {
+    return parsed;
+  }
+
+  // Unknown format, or a bare name as sent by NLST-like listings.
+  let name = line.trim();
+  if name == "." || name == ".." {
+    return None;
+  }
+  Some(entry(name, line.trim()))
+}
+
+fn parse_list_entries_at(lines: Vec<String>, now: NaiveDateTime) -> Vec<FtpEntry> {
+  let mut entries = Vec::new();
+  let mut lines = lines.into_iter().peekable();
+  while let Some(line) = lines.next() {
+    // VMS wraps long names onto their own line, with the details following.
+    let trimmed = line.trim();
+    let wrapped = is_vms_name(trimmed)
+      && lines
+        .peek()
+        .map(|next| next.starts_with(char::is_whitespace) && !next.trim().is_empty())
+        .unwrap_or(false);
+    let line = if wrapped {
+      format!("{} {}", trimmed, lines.next().unwrap_or_default().trim_start())
+    } else {
+      line
+    };
+    if let Some(entry) = parse_list_line(&line, now) {
+      entries.push(entry);
+    }
+  }
+  entries
+}
+
+/// Parses a LIST reply, recognizing Unix, DOS/IIS, NetWare, OpenVMS, IBM i
+/// and z/OS formats. Lines in no known format are kept as bare names.
+pub fn parse_list_entries(lines: Vec<String>) -> Vec<FtpEntry> {
+  let now = chrono::DateTime::from_timestamp_millis(now_millis())
+    .map(|value| value.naive_utc())
+    .unwrap_or_default();
+  parse_list_entries_at(lines, now)
+}
+
+fn mlsd_time(value: &str) -> Option<i64> {
+  let (digits, fraction) = value.split_once('.').unwrap_or((value, ""));
+  let parsed = NaiveDateTime::parse_from_str(digits, "%Y%m%d%H%M%S").ok()?;
+  // `.25` is a quarter second; pad to milliseconds before parsing.
+  let extra: i64 = format!("{:0<3}", fraction.get(..3).unwrap_or(fraction)).parse().unwrap_or(0);
+  Some(millis(parsed) + extra)
+}
+
+/// Parses one MLSD/MLST line of the form `fact=value;fact=value; name`.
+/// Returns `None` for the `cdir`/`pdir` entries.
+pub fn parse_mlsd_entry(line: &str) -> Option<FtpEntry> {
+  let line = line.trim_end_matches(['\r', '\n']).trim_start();
+  let (facts, name) = line.split_once(' ')?;
+  if name.is_empty() {
+    return None;
+  }
+  let mut entry = entry(name, line);
+  let mut uid = None;
+  let mut gid = None;
+  for fact in facts.split(';') {
+    let (key, value) = match fact.split_once('=') {
+      Some(pair) => pair,
+      None => continue,
+    };
+    match key.to_ascii_lowercase().as_str() {
+      "type" => {
+        let kind = value.to_ascii_lowercase();
+        match kind.as_str() {
+          "cdir" | "pdir" => return None,
+          "dir" => entry.is_dir = true,
+          _ if kind.starts_with("os.unix=slink") || kind.starts_with("os.unix=symlink") => {
+            entry.is_symlink = true;
+            entry.link_target = value
+              .split_once(':')
+              .map(|(_, target)| target.to_string())
+              .filter(|target| !target.is_empty());
+          }
+          _ => {}
+        }
+      }
+      "size" => entry.size = value.parse::<u64>().ok(),
+      "modify" => entry.modified = mlsd_time(value),
+      "perm" => entry.permissions = Some(value.to_string()),
+      "unix.mode" => entry.permissions = Some(value.to_string()),
+      "unique" => entry.unique = Some(value.to_string()),
+      "unix.owner" | "unix.ownername" => entry.owner = Some(value.to_string()),
+      "unix.group" | "unix.groupname" => entry.group = Some(value.to_string()),
+      "unix.uid" => uid = Some(value.to_string()),
+      "unix.gid" => gid = Some(value.to_string()),
+      _ => {}
+    }
+  }
+  entry.owner = entry.owner.or(uid);
+  entry.group = entry.group.or(gid);
+  if entry.is_dir {
+    entry.size = None;
+  }
+  Some(entry)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::Value;
+  use std::fs;
+  use std::path::Path;
+
+  fn fixed_now() -> NaiveDateTime {
+    at(2024, 6, 15, 12, 0, 0).unwrap()
+  }
+
+  /// Drops `raw` and unset fields so fixtures only spell out what was parsed.
+  fn compact(value: Value) -> Value {
+    match value {
+      Value::Array(items) => Value::Array(items.into_iter().map(compact).collect()),
+      Value::Object(map) => Value::Object(
+        map
+          .into_iter()
+          .filter(|(key, value)| key != "raw" && !value.is_null() && *value != Value::Bool(false))
+          .collect(),
+      ),
+      other => other,
+    }
+  }
+
+  /// Each `tests/fixtures/listings/<name>.txt` holds a LIST reply captured
+  /// from a server, and `<name>.json` the entries it should produce.
+  #[test]
+  fn parses_listing_fixtures() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/listings");
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+      .unwrap()
+      .map(|entry| entry.unwrap().path())
+      .filter(|path| path.extension().map(|ext| ext == "txt").unwrap_or(false))
+      .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no fixtures in {}", dir.display());
+    for path in paths {
+      let listing = fs::read_to_string(&path).unwrap();
+      let lines = listing.lines().map(str::to_string).collect();
+      let actual = compact(serde_json::to_value(parse_list_entries_at(lines, fixed_now())).unwrap());
+      let expected: Value =
+        serde_json::from_str(&fs::read_to_string(path.with_extension("json")).unwrap()).unwrap();
+      assert_eq!(actual, expected, "fixture {}", path.display());
+    }
+  }
+
+  #[test]
+  fn infers_year_for_recent_unix_dates() {
+    let now = fixed_now();
+    let recent = unix_date("Jun", "10", "08:15", now).unwrap();
+    let last_year = unix_date("Dec", "24", "18:00", now).unwrap();
+    assert_eq!(recent, millis(at(2024, 6, 10, 8, 15, 0).unwrap()));
+    assert_eq!(last_year, millis(at(2023, 12, 24, 18, 0, 0).unwrap()));
+  }
+
+  #[test]
+  fn parses_mlsd_facts() {
+    let line = "type=file;size=1024;modify=20240105123045.250;perm=adfrw;unique=801U2; two  spaces.txt";
+    let entry = parse_mlsd_entry(line).unwrap();
+    assert_eq!(entry.name, "two  spaces.txt");
+    assert_eq!(entry.size, Some(1024));
+    assert_eq!(entry.modified, Some(millis(at(2024, 1, 5, 12, 30, 45).unwrap()) + 250));
+    assert_eq!(entry.permissions.as_deref(), Some("adfrw"));
+    assert_eq!(entry.unique.as_deref(), Some("801U2"));
+
+    let link = parse_mlsd_entry("type=OS.unix=slink:/srv/data;unix.owner=web; data").unwrap();
+    assert!(link.is_symlink);
+    assert_eq!(link.link_target.as_deref(), Some("/srv/data"));
+    assert_eq!(link.owner.as_deref(), Some("web"));
+
+    assert!(parse_mlsd_entry("type=cdir;modify=20240105123045; .").is_none());
+  }
+
+  #[test]
+  fn scales_mlsd_fractional_seconds() {
+    let second = millis(at(2024, 1, 5, 12, 30, 45).unwrap());
+    assert_eq!(mlsd_time("20240105123045.25"), Some(second + 250));
+    assert_eq!(mlsd_time("20240105123045.5"), Some(second + 500));
+    assert_eq!(mlsd_time("20240105123045.123456"), Some(second + 123));
+    assert_eq!(mlsd_time("20240105123045"), Some(second));
+  }
+}
@@ -1,15 +1,12 @@
 ﻿use serde::{Deserialize, Serialize};
 use std::fs::{self, File};
-use std::io::{BufReader, BufWriter, Cursor, Read, Write};
+use std::io::{BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
 use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 use std::path::Path;
-use std::sync::Mutex;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-use suppaftp::types::{FileType, Mode};
-use suppaftp::{FtpError, FtpStream};
-use tauri::{Manager, State, Window};
+use tauri::{AppHandle, Manager, State, Window};
 #[cfg(feature = "system-tray")]
 use tauri::{CustomMenuItem, SystemTray, SystemTrayEvent, SystemTrayMenu};
 use nom_exif::{EntryValue, Exif, ExifIter, ExifTag, MediaParser, MediaSource};
@@ -33,10 +30,41 @@ use windows::Win32::UI::Shell::{
 };
 #[cfg(target_os = "windows")]
 use windows::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
-#[cfg(target_os = "windows")]
-use winreg::enums::HKEY_CURRENT_USER;
-#[cfg(target_os = "windows")]
-use winreg::RegKey;
+
+mod autostart;
+mod cancel;
+mod conflict;
+mod error;
+mod list_parser;
+mod progress;
+mod queue;
+mod remote;
+mod session;
+mod settings;
+mod sites;
+mod sync;
+mod throttle;
+mod tls;
+mod verify;
+mod vault;
+mod watcher;
+
+use autostart::{autostart_status, set_autostart, AutostartStatus};
+use cancel::{Cancellations, Stop};
+use conflict::{Conflict, ConflictDecision, ConflictPrompts, FileInfo};
+use error::{AppError, ErrorKind};
+use progress::ProgressTracker;
+use queue::{EnqueueRequest, QueueSnapshot, TransferDirection, TransferQueue};
+use remote::{fxp_copy, open_remote, Fxp, Protocol, RemoteFs, RemoteStat};
+use session::{Session, SessionRegistry};
+use settings::{ConflictPolicy, Preferences, PreferencesUpdate, Settings};
+use sites::{parse_import_file, ImportReport, PortRange, SiteProfile, SiteStore, TransferMode};
+use sync::{SyncOptions, SyncPlan, SyncReport};
+use throttle::{Throttle, ThrottleConfig, TokenBucket};
+use tls::{CertificateValidation, FtpSecurity};
+use vault::{Vault, VaultStatus};
+use verify::{checksums_match, local_checksum, verify_transfer, VerifyMethod};
+use watcher::{WatchInfo, WatchRegistry};
 
 #[cfg(target_os = "windows")]
 fn apply_window_icon(app: &tauri::App) {
@@ -61,37 +89,77 @@ fn apply_window_icon(app: &tauri::App) {
 
 #[derive(Default)]
 struct AppState {
-  ftp: Mutex<Option<FtpStream>>,
-  cwd: Mutex<String>,
-  prefs: Mutex<UiPreferences>,
-}
-
-#[derive(Debug, Default, Serialize)]
-struct UiPreferences {
-  open_on_startup: bool,
-  close_to_tray: bool,
-  minimize_to_tray: bool,
-}
+  sessions: SessionRegistry,
+  queue: TransferQueue,
+  watches: WatchRegistry,
+  sites: SiteStore,
+  vault: Vault,
+  conflicts: ConflictPrompts,
+  cancels: Cancellations,
+  throttle: Throttle,
+  progress: ProgressTracker,
+  prefs: Settings,
+  startup_log: StartupLog,
+}
+
+/// Problems met while loading saved state, before the window listens for
+/// `log` events. The frontend collects them once it does.
+#[derive(Default)]
+struct StartupLog(std::sync::Mutex<Vec<LogEntry>>);
+
+impl StartupLog {
+  fn error(&self, message: impl Into<String>) {
+    if let Ok(mut entries) = self.0.lock() {
+      entries.push(LogEntry {
+        level: "error".to_string(),
+        message: message.into(),
+        timestamp: now_millis(),
+      });
+    }
+  }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct UiPreferencesInput {
-  open_on_startup: bool,
-  close_to_tray: bool,
-  minimize_to_tray: bool,
+  fn take(&self) -> Vec<LogEntry> {
+    self.0.lock().map(|mut entries| std::mem::take(&mut *entries)).unwrap_or_default()
+  }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct ConnectConfig {
   host: String,
   port: u16,
   username: String,
+  #[serde(default)]
   password: String,
+  /// Vault reference used when `password` is empty.
+  #[serde(default)]
+  password_ref: Option<String>,
+  #[serde(default)]
+  protocol: Protocol,
+  #[serde(default)]
+  security: FtpSecurity,
+  #[serde(default)]
+  certificate: CertificateValidation,
+  #[serde(default)]
+  timeout_secs: Option<u64>,
+  #[serde(default)]
+  transfer_mode: TransferMode,
+  #[serde(default)]
+  active_ports: Option<PortRange>,
+  #[serde(default)]
+  external_ip: Option<String>,
+}
+
+impl ConnectConfig {
+  fn timeout(&self) -> Duration {
+    Duration::from_secs(self.timeout_secs.unwrap_or(10).max(1))
+  }
 }
 
 #[derive(Debug, Serialize)]
 struct ConnectResponse {
+  session_id: String,
   cwd: String,
+  local_dir: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -100,12 +168,19 @@ struct ListResponse {
   entries: Vec<FtpEntry>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
 struct FtpEntry {
   name: String,
   size: Option<u64>,
-  modified: Option<String>,
+  /// Milliseconds since the Unix epoch, UTC.
+  modified: Option<i64>,
   is_dir: bool,
+  is_symlink: bool,
+  link_target: Option<String>,
+  permissions: Option<String>,
+  owner: Option<String>,
+  group: Option<String>,
+  unique: Option<String>,
   raw: Option<String>,
 }
 
@@ -143,21 +218,25 @@ struct LogEntry {
 }
 
 #[derive(Clone, Debug, Serialize)]
-struct TransferProgress {
+struct TransferDone {
   id: String,
-  transferred: u64,
-  total: Option<u64>,
+  /// How the result was checked; `None` when verification was off, the file
+  /// was skipped or the server reports neither a checksum nor a size.
+  verified: Option<VerifyMethod>,
 }
 
 #[derive(Clone, Debug, Serialize)]
-struct TransferDone {
+struct TransferCancelled {
   id: String,
+  /// Whether the partial file was left in place so a retry can resume it.
+  partial_kept: bool,
 }
 
 #[derive(Clone, Debug, Serialize)]
 struct TransferErrorPayload {
   id: String,
-  message: String,
+  #[serde(flatten)]
+  error: AppError,
 }
 
 fn now_millis() -> i64 {
@@ -282,137 +361,76 @@ fn log_event(window: &Window, level: &str, message: impl Into<String>) {
 }
 
 fn emit_progress(window: &Window, id: &str, transferred: u64, total: Option<u64>) {
-  let payload = TransferProgress {
-    id: id.to_string(),
-    transferred,
-    total,
-  };
-  let _ = window.emit("transfer-progress", payload);
+  let progress = window.state::<AppState>().progress.transfer(id, transferred, total);
+  let _ = window.emit("transfer-progress", progress);
+}
+
+/// Reports a directory copy or sync run as a whole, after each file.
+fn emit_batch(
+  window: &Window,
+  id: &str,
+  files: (usize, usize),
+  bytes_done: u64,
+  bytes_total: Option<u64>,
+) {
+  let progress = window.state::<AppState>().progress.batch(id, files.0, files.1, bytes_done, bytes_total);
+  let _ = window.emit("batch-progress", progress);
 }
 
 fn emit_done(window: &Window, id: &str) {
-  let _ = window.emit("transfer-complete", TransferDone { id: id.to_string() });
+  emit_verified(window, id, None);
 }
 
-fn emit_error(window: &Window, id: &str, message: impl Into<String>) {
+fn emit_verified(window: &Window, id: &str, verified: Option<VerifyMethod>) {
+  window.state::<AppState>().progress.finish(id);
   let _ = window.emit(
-    "transfer-error",
-    TransferErrorPayload {
+    "transfer-complete",
+    TransferDone {
       id: id.to_string(),
-      message: message.into(),
+      verified,
     },
   );
 }
 
-fn map_err<E: std::fmt::Display>(err: E) -> String {
-  err.to_string()
-}
-
-#[cfg(target_os = "windows")]
-fn set_autostart_windows(enable: bool) -> Result<(), String> {
-  let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-  let (key, _) = hkcu
-    .create_subkey("Software\\Microsoft\\Windows\\CurrentVersion\\Run")
-    .map_err(map_err)?;
-  let app_name = "FTPBrowser";
-  if enable {
-    let exe = std::env::current_exe().map_err(map_err)?;
-    let exe_str = exe.to_string_lossy().to_string();
-    key.set_value(app_name, &exe_str).map_err(map_err)?;
-  } else {
-    let _ = key.delete_value(app_name);
-  }
-  Ok(())
-}
-
-fn set_autostart(enable: bool) -> Result<(), String> {
-  #[cfg(target_os = "windows")]
-  {
-    return set_autostart_windows(enable);
-  }
-  #[cfg(not(target_os = "windows"))]
-  {
-    let _ = enable;
-    return Ok(());
-  }
-}
-
-fn normalize_line_name(name: String) -> String {
-  let trimmed = name.trim();
-  if trimmed == "." || trimmed == ".." {
-    String::new()
-  } else {
-    trimmed.to_string()
-  }
+fn emit_error(window: &Window, id: &str, error: impl Into<AppError>) {
+  window.state::<AppState>().progress.finish(id);
+  let _ = window.emit(
+    "transfer-error",
+    TransferErrorPayload {
+      id: id.to_string(),
+      error: error.into(),
+    },
+  );
 }
 
-fn parse_list_entry(line: &str) -> Option<FtpEntry> {
-  let trimmed = line.trim();
-  if trimmed.is_empty() {
-    return None;
-  }
-
-  let parts: Vec<&str> = trimmed.split_whitespace().collect();
-  if parts.len() >= 4 && (parts[0].contains('-') || parts[0].contains('/')) && parts[1].contains(':') {
-    let is_dir = parts[2].eq_ignore_ascii_case("<DIR>");
-    let size = if is_dir { None } else { parts[2].replace(',', "").parse::<u64>().ok() };
-    let name = normalize_line_name(parts[3..].join(" "));
-    if name.is_empty() {
-      return None;
-    }
-    return Some(FtpEntry {
-      name,
-      size,
-      modified: Some(format!("{} {}", parts[0], parts[1])),
-      is_dir,
-      raw: Some(trimmed.to_string()),
-    });
-  }
-
-  if parts.len() >= 9 {
-    let marker = parts[0];
-    let is_dir = marker.starts_with('d');
-    let size = parts[4].parse::<u64>().ok();
-    let modified = Some(format!("{} {} {}", parts[5], parts[6], parts[7]));
-    let name = normalize_line_name(parts[8..].join(" "));
-    if name.is_empty() {
-      return None;
-    }
-    return Some(FtpEntry {
-      name,
-      size,
-      modified,
-      is_dir,
-      raw: Some(trimmed.to_string()),
-    });
-  }
-
-  Some(FtpEntry {
-    name: trimmed.to_string(),
-    size: None,
-    modified: None,
-    is_dir: false,
-    raw: Some(trimmed.to_string()),
-  })
+fn emit_cancelled(window: &Window, id: &str, partial_kept: bool) {
+  window.state::<AppState>().progress.finish(id);
+  let _ = window.emit(
+    "transfer-cancelled",
+    TransferCancelled {
+      id: id.to_string(),
+      partial_kept,
+    },
+  );
 }
 
-fn parse_list_entries(lines: Vec<String>) -> Vec<FtpEntry> {
-  lines
-    .into_iter()
-    .filter_map(|line| parse_list_entry(&line))
-    .collect()
+fn map_err<E: Into<AppError>>(err: E) -> AppError {
+  err.into()
 }
 
-fn connect_with_timeout(host: &str, port: u16, timeout: Duration) -> Result<FtpStream, String> {
+fn connect_tcp(host: &str, port: u16, timeout: Duration) -> Result<TcpStream, AppError> {
   let addrs: Vec<SocketAddr> = (host, port)
     .to_socket_addrs()
     .map_err(map_err)?
     .collect();
   if addrs.is_empty() {
-    return Err(format!("Unable to resolve address: {}:{}", host, port));
+    return Err(AppError::new(
+      ErrorKind::NotConnected,
+      format!("Unable to resolve address: {}:{}", host, port),
+    ));
   }
 
-  let mut last_err: Option<String> = None;
+  let mut last_err: Option<AppError> = None;
   let mut ordered = addrs;
   ordered.sort_by_key(|addr| if addr.is_ipv4() { 0 } else { 1 });
 
@@ -426,44 +444,68 @@ fn connect_with_timeout(host: &str, port: u16, timeout: Duration) -> Result<FtpS
           .set_write_timeout(Some(timeout))
           .map_err(map_err)?;
         let _ = stream.set_nodelay(true);
-        return FtpStream::connect_with_stream(stream).map_err(map_err);
+        return Ok(stream);
       }
       Err(err) => last_err = Some(map_err(err)),
     }
   }
 
-  Err(last_err.unwrap_or_else(|| "Failed to connect".to_string()))
+  Err(last_err.unwrap_or_else(|| AppError::new(ErrorKind::NotConnected, "Failed to connect")))
 }
 
 #[tauri::command]
 fn connect(
   state: State<'_, AppState>,
   window: Window,
-  config: ConnectConfig,
-) -> Result<ConnectResponse, String> {
+  config: Option<ConnectConfig>,
+  site_id: Option<String>,
+  password: Option<String>,
+) -> Result<ConnectResponse, AppError> {
+  let (mut config, site) = match (config, site_id) {
+    (Some(config), _) => (config, None),
+    (None, Some(site_id)) => {
+      let site = state.sites.get(&site_id)?;
+      (site.connect_config(), Some(site))
+    }
+    (None, None) => return Err("Missing connection settings".into()),
+  };
+  if let Some(password) = password {
+    config.password = password;
+  }
+  if config.timeout_secs.is_none() {
+    config.timeout_secs = Some(state.prefs.get()?.transfers.timeout_secs);
+  }
+  if config.password.is_empty() {
+    if let Some(reference) = config.password_ref.as_deref() {
+      config.password = state.vault.get(reference)?;
+    }
+  }
   let host = config.host.trim();
   let address = format!("{}:{}", host, config.port);
   log_event(&window, "info", format!("Connecting to {}", address));
-  let mut ftp = connect_with_timeout(host, config.port, Duration::from_secs(10))?;
-  ftp
-    .login(&config.username, &config.password)
-    .map_err(map_err)?;
-  ftp.set_passive_nat_workaround(true);
-  ftp.transfer_type(FileType::Binary).map_err(map_err)?;
-  let cwd = normalize_cwd(ftp.pwd().map_err(map_err)?);
-  *state.cwd.lock().map_err(map_err)? = cwd.clone();
-  *state.ftp.lock().map_err(map_err)? = Some(ftp);
+  let mut remote = open_remote(&window, &config, config.timeout())?;
+  if let Some(dir) = site.as_ref().and_then(|site| site.remote_dir.as_deref()) {
+    if !dir.trim().is_empty() {
+      if let Err(err) = remote.cwd(dir) {
+        log_event(&window, "error", format!("Unable to open {}: {}", dir, err));
+      }
+    }
+  }
+  let cwd = remote.pwd()?;
+  let session_id = state.sessions.insert(config, remote, cwd.clone())?;
   log_event(&window, "success", "Connected");
-  Ok(ConnectResponse { cwd })
+  Ok(ConnectResponse {
+    session_id,
+    cwd,
+    local_dir: site.and_then(|site| site.local_dir),
+  })
 }
 
 #[tauri::command]
-fn disconnect(state: State<'_, AppState>, window: Window) -> Result<(), String> {
-  let mut ftp_guard = state.ftp.lock().map_err(map_err)?;
-  if let Some(mut ftp) = ftp_guard.take() {
-    let _ = ftp.quit();
+fn disconnect(state: State<'_, AppState>, window: Window, session_id: String) -> Result<(), AppError> {
+  if let Some(session) = state.sessions.remove(&session_id)? {
+    session.close();
   }
-  *state.cwd.lock().map_err(map_err)? = String::new();
   log_event(&window, "info", "Disconnected");
   Ok(())
 }
@@ -472,38 +514,51 @@ fn disconnect(state: State<'_, AppState>, window: Window) -> Result<(), String>
 fn list_dir(
   state: State<'_, AppState>,
   window: Window,
+  session_id: String,
   path: Option<String>,
-) -> Result<ListResponse, String> {
-  let mut ftp_guard = state.ftp.lock().map_err(map_err)?;
-  let ftp = ftp_guard.as_mut().ok_or("Not connected")?;
-  if let Some(target) = path {
-    if !target.trim().is_empty() {
-      ftp.cwd(target).map_err(map_err)?;
-    }
-  }
-  let cwd = normalize_cwd(ftp.pwd().map_err(map_err)?);
-  *state.cwd.lock().map_err(map_err)? = cwd.clone();
-  let listing = ftp.list(None).map_err(map_err)?;
-  let entries = parse_list_entries(listing);
+) -> Result<ListResponse, AppError> {
+  let session = state.sessions.get(&session_id)?;
+  let (cwd, entries) = session.with_remote(&window, true, |remote| {
+    if let Some(target) = path.as_deref() {
+      if !target.trim().is_empty() {
+        remote.cwd(target)?;
+      }
+    }
+    let cwd = remote.pwd()?;
+    let entries = remote.list(None)?;
+    // Only now, so a retry after reconnecting resolves a relative `path`
+    // against the folder it was meant for.
+    *session.cwd.lock().map_err(map_err)? = cwd.clone();
+    Ok((cwd, entries))
+  })?;
   log_event(&window, "info", format!("Listed {} items", entries.len()));
   Ok(ListResponse { cwd, entries })
 }
 
 #[tauri::command]
-fn create_dir(state: State<'_, AppState>, window: Window, path: String) -> Result<(), String> {
-  let mut ftp_guard = state.ftp.lock().map_err(map_err)?;
-  let ftp = ftp_guard.as_mut().ok_or("Not connected")?;
-  ftp.mkdir(path).map_err(map_err)?;
+fn create_dir(
+  state: State<'_, AppState>,
+  window: Window,
+  session_id: String,
+  path: String,
+) -> Result<(), AppError> {
+  let session = state.sessions.get(&session_id)?;
+  session.with_remote(&window, false, |remote| remote.mkdir(&path))?;
   log_event(&window, "success", "Directory created");
   Ok(())
 }
 
 #[tauri::command]
-fn create_remote_file(state: State<'_, AppState>, window: Window, path: String) -> Result<(), String> {
-  let mut ftp_guard = state.ftp.lock().map_err(map_err)?;
-  let ftp = ftp_guard.as_mut().ok_or("Not connected")?;
-  let mut reader = Cursor::new(Vec::<u8>::new());
-  ftp.put_file(path, &mut reader).map_err(map_err)?;
+fn create_remote_file(
+  state: State<'_, AppState>,
+  window: Window,
+  session_id: String,
+  path: String,
+) -> Result<(), AppError> {
+  let session = state.sessions.get(&session_id)?;
+  session.with_remote(&window, false, |remote| {
+    remote.put(&path, 0, &mut Cursor::new(Vec::<u8>::new()))
+  })?;
   log_event(&window, "success", "Remote file created");
   Ok(())
 }
@@ -516,94 +571,236 @@ fn join_remote(base: &str, name: &str) -> String {
   }
 }
 
-fn copy_remote_file(ftp: &mut FtpStream, from: &str, to: &str) -> Result<(), String> {
-  let tmp = tempfile::tempfile().map_err(map_err)?;
-  let mut writer = BufWriter::new(tmp);
-  ftp
-    .retr(from, |reader| {
-      std::io::copy(reader, &mut writer)
-        .map(|_| ())
-        .map_err(FtpError::ConnectionError)
-    })
-    .map_err(map_err)?;
-  writer.flush().map_err(map_err)?;
-  let mut tmp = writer.into_inner().map_err(map_err)?;
-  use std::io::Seek;
-  tmp.seek(std::io::SeekFrom::Start(0)).map_err(map_err)?;
-  let mut reader = BufReader::new(tmp);
-  ftp.put_file(to.to_string(), &mut reader).map_err(map_err)?;
-  Ok(())
+/// One step of a remote copy, listed up front so progress has a total.
+struct CopyItem {
+  from: String,
+  to: String,
+  is_dir: bool,
+  size: Option<u64>,
 }
 
 const MAX_REMOTE_DEPTH: usize = 20;
 
-fn copy_remote_dir(ftp: &mut FtpStream, from: &str, to: &str) -> Result<(), String> {
-  copy_remote_dir_inner(ftp, from, to, 0)
-}
-
-fn copy_remote_dir_inner(ftp: &mut FtpStream, from: &str, to: &str, depth: usize) -> Result<(), String> {
+fn plan_remote_copy(
+  source: &mut dyn RemoteFs,
+  from: &str,
+  to: &str,
+  depth: usize,
+  items: &mut Vec<CopyItem>,
+) -> Result<(), AppError> {
   if depth >= MAX_REMOTE_DEPTH {
     return Err("Directory nesting too deep (max 20 levels)".into());
   }
-  let _ = ftp.mkdir(to);
-  let listing = ftp.list(Some(from)).map_err(map_err)?;
-  let entries = parse_list_entries(listing);
+  items.push(CopyItem {
+    from: from.to_string(),
+    to: to.to_string(),
+    is_dir: true,
+    size: None,
+  });
+  let entries = source.list(Some(from))?;
   for entry in entries {
     if entry.name == "." || entry.name == ".." {
       continue;
     }
+    // Links are not copied; following them could leave the tree or loop.
+    if entry.is_symlink {
+      continue;
+    }
     let from_path = join_remote(from, &entry.name);
     let to_path = join_remote(to, &entry.name);
     if entry.is_dir {
-      copy_remote_dir_inner(ftp, &from_path, &to_path, depth + 1)?;
+      plan_remote_copy(source, &from_path, &to_path, depth + 1, items)?;
     } else {
-      copy_remote_file(ftp, &from_path, &to_path)?;
+      items.push(CopyItem {
+        from: from_path,
+        to: to_path,
+        is_dir: false,
+        size: entry.size,
+      });
     }
   }
   Ok(())
 }
 
+/// Copies between two connections, server to server with FXP where both ends
+/// allow it and otherwise by piping the download straight into the upload.
+/// Workers left out of step by a refused FXP are replaced from their sessions.
+#[allow(clippy::too_many_arguments)]
+fn copy_between(
+  sessions: (&Session, &Session),
+  source: &mut Box<dyn RemoteFs>,
+  target: &mut Box<dyn RemoteFs>,
+  window: &Window,
+  id: &str,
+  from: &str,
+  to: &str,
+  is_dir: bool,
+) -> Result<(), AppError> {
+  let cancels = &window.state::<AppState>().cancels;
+  let mut items = Vec::new();
+  if is_dir {
+    plan_remote_copy(source.as_mut(), from, to, 0, &mut items)?;
+  } else {
+    items.push(CopyItem {
+      from: from.to_string(),
+      to: to.to_string(),
+      is_dir: false,
+      size: source.stat(from).ok().and_then(|stat| stat.size),
+    });
+  }
+  let total = Some(items.iter().filter_map(|item| item.size).sum::<u64>());
+  let files_total = items.iter().filter(|item| !item.is_dir).count();
+  let mut files_done = 0;
+  let mut copied = 0u64;
+  let mut try_fxp = true;
+  emit_progress(window, id, 0, total);
+
+  for item in items {
+    // Relayed files stop within a chunk; FXP copies only between files.
+    if cancels.is_cancelled(id) {
+      return Err(cancel::cancelled());
+    }
+    if item.is_dir {
+      if let Err(err) = target.mkdir(&item.to) {
+        // Already there is fine.
+        match target.stat(&item.to) {
+          Ok(stat) if stat.is_dir => {}
+          _ => return Err(err),
+        }
+      }
+      continue;
+    }
+    let mut relayed = true;
+    if try_fxp {
+      if let (Some(ftp_source), Some(ftp_target)) = (source.as_ftp(), target.as_ftp()) {
+        match fxp_copy(ftp_source, ftp_target, &item.from, &item.to)? {
+          Fxp::Copied => {
+            copied += item.size.unwrap_or(0);
+            emit_progress(window, id, copied, total);
+            relayed = false;
+          }
+          outcome => {
+            log_event(window, "info", "Server-to-server copy refused, streaming instead");
+            try_fxp = false;
+            if outcome == Fxp::Abandoned {
+              *source = sessions.0.checkout_worker(window)?;
+              *target = sessions.1.checkout_worker(window)?;
+            }
+          }
+        }
+      } else {
+        try_fxp = false;
+      }
+    }
+    if relayed {
+      let mut written = 0;
+      source.get(&item.from, 0, &mut |reader| {
+        let mut reader = ProgressReader::new(reader, window.clone(), id.to_string(), total).starting_at(copied);
+        written = target.put(&item.to, 0, &mut reader).map_err(std::io::Error::other)?;
+        Ok(written)
+      })?;
+      copied += written;
+    }
+    files_done += 1;
+    if is_dir {
+      emit_batch(window, id, (files_done, files_total), copied, total);
+    }
+  }
+  emit_progress(window, id, copied, total);
+  Ok(())
+}
+
+/// Copies on two worker connections, one reading and one writing, which may
+/// belong to the same session.
+fn run_copy(
+  source: &Session,
+  target: &Session,
+  window: &Window,
+  id: &str,
+  from: &str,
+  to: &str,
+  is_dir: bool,
+) -> Result<(), AppError> {
+  let from = source.resolve_path(from);
+  let to = target.resolve_path(to);
+  let cancels = &window.state::<AppState>().cancels;
+  cancels.begin(id);
+  let result = source.checkout_worker(window).and_then(|mut reader| {
+    let mut writer = target.checkout_worker(window)?;
+    let result = copy_between((source, target), &mut reader, &mut writer, window, id, &from, &to, is_dir);
+    if result.is_ok() {
+      source.checkin_worker(reader);
+      target.checkin_worker(writer);
+    }
+    result
+  });
+  let cancelled = cancels.finish(id).is_some();
+  match result {
+    Ok(_) => {
+      emit_done(window, id);
+      log_event(window, "success", "Remote copy completed");
+      Ok(())
+    }
+    Err(_) if cancelled => {
+      emit_cancelled(window, id, true);
+      log_event(window, "info", "Remote copy cancelled");
+      Err(cancel::cancelled())
+    }
+    Err(err) => {
+      emit_error(window, id, err.clone());
+      Err(err)
+    }
+  }
+}
+
+/// `id` names the copy in progress events; one is generated when omitted.
 #[tauri::command]
-fn copy_remote(
+#[allow(clippy::too_many_arguments)]
+async fn copy_remote(
   state: State<'_, AppState>,
   window: Window,
+  session_id: String,
   from: String,
   to: String,
   is_dir: bool,
-) -> Result<(), String> {
-  let mut ftp_guard = state.ftp.lock().map_err(map_err)?;
-  let ftp = ftp_guard.as_mut().ok_or("Not connected")?;
-  if is_dir {
-    copy_remote_dir(ftp, &from, &to)?;
-  } else {
-    copy_remote_file(ftp, &from, &to)?;
-  }
-  log_event(&window, "success", "Remote copy completed");
-  Ok(())
+  target_session_id: Option<String>,
+  id: Option<String>,
+) -> Result<(), AppError> {
+  let source = state.sessions.get(&session_id)?;
+  let target = match target_session_id {
+    Some(target_id) => state.sessions.get(&target_id)?,
+    None => source.clone(),
+  };
+  let id = id.unwrap_or_else(|| format!("copy-{}", now_millis()));
+  tauri::async_runtime::spawn_blocking(move || {
+    run_copy(&source, &target, &window, &id, &from, &to, is_dir)
+  })
+  .await
+  .map_err(map_err)?
 }
 
-fn delete_remote_dir(ftp: &mut FtpStream, path: &str) -> Result<(), String> {
-  delete_remote_dir_inner(ftp, path, 0)
+fn delete_remote_dir(remote: &mut dyn RemoteFs, path: &str) -> Result<(), AppError> {
+  delete_remote_dir_inner(remote, path, 0)
 }
 
-fn delete_remote_dir_inner(ftp: &mut FtpStream, path: &str, depth: usize) -> Result<(), String> {
+fn delete_remote_dir_inner(remote: &mut dyn RemoteFs, path: &str, depth: usize) -> Result<(), AppError> {
   if depth >= MAX_REMOTE_DEPTH {
     return Err("Directory nesting too deep (max 20 levels)".into());
   }
-  let listing = ftp.list(Some(path)).map_err(map_err)?;
-  let entries = parse_list_entries(listing);
+  let entries = remote.list(Some(path))?;
   for entry in entries {
     if entry.name == "." || entry.name == ".." {
       continue;
     }
     let child = join_remote(path, &entry.name);
-    if entry.is_dir {
-      delete_remote_dir_inner(ftp, &child, depth + 1)?;
+    // A link is removed itself, never the tree it points to.
+    if entry.is_dir && !entry.is_symlink {
+      delete_remote_dir_inner(remote, &child, depth + 1)?;
     } else {
-      ftp.rm(child).map_err(map_err)?;
+      remote.rm(&child)?;
     }
   }
-  ftp.rmdir(path).map_err(map_err)?;
+  remote.rmdir(path)?;
   Ok(())
 }
 
@@ -611,16 +808,18 @@ fn delete_remote_dir_inner(ftp: &mut FtpStream, path: &str, depth: usize) -> Res
 fn delete_path(
   state: State<'_, AppState>,
   window: Window,
+  session_id: String,
   path: String,
   is_dir: bool,
-) -> Result<(), String> {
-  let mut ftp_guard = state.ftp.lock().map_err(map_err)?;
-  let ftp = ftp_guard.as_mut().ok_or("Not connected")?;
-  if is_dir {
-    delete_remote_dir(ftp, &path)?;
-  } else {
-    ftp.rm(path).map_err(map_err)?;
-  }
+) -> Result<(), AppError> {
+  let session = state.sessions.get(&session_id)?;
+  session.with_remote(&window, false, |remote| {
+    if is_dir {
+      delete_remote_dir(remote, &path)
+    } else {
+      remote.rm(&path)
+    }
+  })?;
   log_event(&window, "success", "Remote item removed");
   Ok(())
 }
@@ -629,12 +828,12 @@ fn delete_path(
 fn rename_path(
   state: State<'_, AppState>,
   window: Window,
+  session_id: String,
   from: String,
   to: String,
-) -> Result<(), String> {
-  let mut ftp_guard = state.ftp.lock().map_err(map_err)?;
-  let ftp = ftp_guard.as_mut().ok_or("Not connected")?;
-  ftp.rename(from, to).map_err(map_err)?;
+) -> Result<(), AppError> {
+  let session = state.sessions.get(&session_id)?;
+  session.with_remote(&window, false, |remote| remote.rename(&from, &to))?;
   log_event(&window, "success", "Remote item renamed");
   Ok(())
 }
@@ -644,22 +843,38 @@ fn copy_with_progress(
   writer: &mut dyn Write,
   window: &Window,
   id: &str,
+  start: u64,
   total: Option<u64>,
-) -> Result<u64, FtpError> {
-  let mut buffer = [0u8; 128 * 1024];
-  let mut transferred = 0u64;
-  let mut last_emit = 0u64;
+) -> std::io::Result<u64> {
+  let state = window.try_state::<AppState>();
+  let buffer_size = state
+    .as_ref()
+    .and_then(|state| state.prefs.get().ok())
+    .map(|prefs| prefs.transfers.buffer_size)
+    .unwrap_or(128 * 1024);
+  let mut buffer = vec![0u8; buffer_size];
+  let mut bucket = TokenBucket::default();
+  let mut transferred = start;
+  let mut last_emit = start;
   let mut last_tick = Instant::now();
 
   loop {
-    let read = reader.read(&mut buffer).map_err(FtpError::ConnectionError)?;
+    if state.as_ref().is_some_and(|state| state.cancels.is_cancelled(id)) {
+      return Err(std::io::Error::other(cancel::cancelled()));
+    }
+    let read = reader.read(&mut buffer)?;
     if read == 0 {
       break;
     }
-    writer
-      .write_all(&buffer[..read])
-      .map_err(FtpError::ConnectionError)?;
+    writer.write_all(&buffer[..read])?;
     transferred += read as u64;
+    if let Some(state) = &state {
+      state
+        .throttle
+        .consume(TransferDirection::Download, &mut bucket, read, || {
+          state.cancels.is_cancelled(id)
+        });
+    }
 
     if transferred - last_emit >= 512 * 1024 || last_tick.elapsed() > Duration::from_millis(500) {
       emit_progress(window, id, transferred, total);
@@ -669,9 +884,11 @@ fn copy_with_progress(
   }
 
   emit_progress(window, id, transferred, total);
-  Ok(transferred)
+  Ok(transferred - start)
 }
 
+/// Reports progress for data flowing out through the uplink, applies the
+/// upload rate limits to it and stops it once the transfer is cancelled.
 struct ProgressReader<R> {
   inner: R,
   window: Window,
@@ -680,6 +897,7 @@ struct ProgressReader<R> {
   transferred: u64,
   last_emit: u64,
   last_tick: Instant,
+  bucket: TokenBucket,
 }
 
 impl<R> ProgressReader<R> {
@@ -692,14 +910,33 @@ impl<R> ProgressReader<R> {
       transferred: 0,
       last_emit: 0,
       last_tick: Instant::now(),
+      bucket: TokenBucket::default(),
     }
   }
+
+  /// Counts from `offset`, for resumed transfers and multi-file totals.
+  fn starting_at(mut self, offset: u64) -> Self {
+    self.transferred = offset;
+    self.last_emit = offset;
+    self
+  }
 }
 
 impl<R: Read> Read for ProgressReader<R> {
   fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    let state = self.window.try_state::<AppState>();
+    if state.as_ref().is_some_and(|state| state.cancels.is_cancelled(&self.id)) {
+      return Err(std::io::Error::other(cancel::cancelled()));
+    }
     let read = self.inner.read(buf)?;
     if read > 0 {
+      if let Some(state) = state {
+        state
+          .throttle
+          .consume(TransferDirection::Upload, &mut self.bucket, read, || {
+            state.cancels.is_cancelled(&self.id)
+          });
+      }
       self.transferred += read as u64;
       if self.transferred - self.last_emit >= 512 * 1024
         || self.last_tick.elapsed() > Duration::from_millis(500)
@@ -713,117 +950,663 @@ impl<R: Read> Read for ProgressReader<R> {
   }
 }
 
-#[tauri::command]
-fn download_file(
-  state: State<'_, AppState>,
-  window: Window,
-  id: String,
-  remote_path: String,
-  local_path: String,
-) -> Result<(), String> {
-  let mut ftp_guard = state.ftp.lock().map_err(map_err)?;
-  let ftp = ftp_guard.as_mut().ok_or("Not connected")?;
+/// Where a download continues from a leftover `.part` file, the prefix of an
+/// earlier attempt. It is trusted only while it can still belong to the remote
+/// file: no longer than it, not older than its last change, and, when already
+/// complete, confirmed by the server's checksum.
+fn resume_offset(remote: &mut dyn RemoteFs, remote_path: &str, part: &Path, stat: Option<&RemoteStat>) -> u64 {
+  let meta = match fs::metadata(part) {
+    Ok(meta) => meta,
+    Err(_) => return 0,
+  };
+  let offset = meta.len();
+  let stat = match stat {
+    Some(stat) => stat,
+    None => return 0,
+  };
+  let size = match stat.size {
+    Some(size) if offset <= size => size,
+    _ => return 0,
+  };
+  let written = meta.modified().ok().and_then(system_time_millis);
+  if let (Some(modified), Some(written)) = (stat.modified, written) {
+    if modified > written {
+      return 0;
+    }
+  }
+  if offset == size {
+    let confirmed = match remote.checksum(remote_path) {
+      Ok(Some((method, remote_sum))) => {
+        local_checksum(part, method).is_ok_and(|local_sum| checksums_match(method, &local_sum, &remote_sum))
+      }
+      _ => false,
+    };
+    if !confirmed {
+      return 0;
+    }
+  }
+  offset
+}
 
-  let total = ftp.size(&remote_path).ok().map(|value| value as u64);
-  let target = Path::new(&local_path);
+fn download_with(
+  remote: &mut dyn RemoteFs,
+  window: &Window,
+  id: &str,
+  remote_path: &str,
+  local_path: &str,
+) -> Result<(), AppError> {
+  let stat = remote.stat(remote_path).ok();
+  if stat.as_ref().map(|value| value.is_dir).unwrap_or(false) {
+    return Err(format!("{} is a directory", remote_path).into());
+  }
+  let total = stat.as_ref().and_then(|value| value.size);
+  let target = Path::new(local_path);
   if let Some(parent) = target.parent() {
     fs::create_dir_all(parent).map_err(map_err)?;
   }
   let tmp_path = format!("{}.part", local_path);
-  let file = File::create(&tmp_path).map_err(map_err)?;
+  let offset = resume_offset(remote, remote_path, Path::new(&tmp_path), stat.as_ref());
+  let file = if offset > 0 {
+    log_event(window, "info", format!("Resuming {} at {} bytes", remote_path, offset));
+    fs::OpenOptions::new().append(true).open(&tmp_path)
+  } else {
+    File::create(&tmp_path)
+  }
+  .map_err(map_err)?;
   let mut writer = BufWriter::with_capacity(256 * 1024, file);
-  let window_clone = window.clone();
-  let id_clone = id.clone();
 
-  let result = ftp.retr(&remote_path, |reader| {
-    let result = copy_with_progress(reader, &mut writer, &window_clone, &id_clone, total);
-    writer.flush().map_err(FtpError::ConnectionError)?;
-    result
-  });
+  let result = if Some(offset) == total {
+    emit_progress(window, id, offset, total);
+    Ok(0)
+  } else {
+    remote.get(remote_path, offset, &mut |reader| {
+      let result = copy_with_progress(reader, &mut writer, window, id, offset, total);
+      writer.flush()?;
+      result
+    })
+  };
 
   // Explicitly extract the inner file, flush, and drop before attempting to rename or delete 
   // to avoid Windows file lock errors
-  let inner_file_to_drop = writer.into_inner().unwrap_or_else(|e| e.into_inner());
+  let inner_file_to_drop = writer
+    .into_inner()
+    .unwrap_or_else(|e| e.into_inner().into_parts().0);
   drop(inner_file_to_drop);
 
+  // The .part file is kept on failure so the next attempt can resume it,
+  // and after a cancel unless the settings say otherwise.
+  if result.is_err() && discard_partial(window, id) {
+    let _ = fs::remove_file(&tmp_path);
+  }
+  result?;
+  let written = fs::metadata(&tmp_path).map_err(map_err)?.len();
+  if let Some(size) = total {
+    if written != size {
+      return Err(AppError::new(
+        ErrorKind::Other,
+        format!("Incomplete download of {}: {} of {} bytes", remote_path, written, size),
+      ));
+    }
+  }
+  fs::rename(&tmp_path, local_path).map_err(map_err)
+}
+
+fn conflict_policy(window: &Window, policy: Option<ConflictPolicy>) -> Result<ConflictPolicy, AppError> {
+  match policy {
+    Some(policy) => Ok(policy),
+    None => Ok(window.state::<AppState>().prefs.get()?.transfers.conflict_policy),
+  }
+}
+
+fn verify_enabled(window: &Window, verify: Option<bool>) -> Result<bool, AppError> {
+  match verify {
+    Some(verify) => Ok(verify),
+    None => Ok(window.state::<AppState>().prefs.get()?.transfers.verify),
+  }
+}
+
+fn keep_partial(window: &Window) -> bool {
+  window
+    .state::<AppState>()
+    .prefs
+    .get()
+    .map(|prefs| prefs.transfers.keep_partial_on_cancel)
+    .unwrap_or(true)
+}
+
+/// Whether a failed transfer was cancelled and its partial file should go.
+/// A paused transfer always keeps it.
+fn discard_partial(window: &Window, id: &str) -> bool {
+  window.state::<AppState>().cancels.stop(id) == Some(Stop::Cancel) && !keep_partial(window)
+}
+
+/// Checks a finished transfer when enabled. A copy that fails the check is
+/// removed so a retry starts over instead of resuming it.
+fn verify_after(
+  remote: &mut dyn RemoteFs,
+  window: &Window,
+  direction: TransferDirection,
+  local_path: &str,
+  remote_path: &str,
+) -> Result<Option<VerifyMethod>, AppError> {
+  let result = verify_transfer(remote, local_path, remote_path);
+  if let Err(err) = &result {
+    if err.kind == ErrorKind::VerifyMismatch {
+      let removed = match direction {
+        TransferDirection::Download => fs::remove_file(local_path).map_err(map_err),
+        TransferDirection::Upload => remote.rm(remote_path),
+      };
+      if removed.is_ok() {
+        let path = match direction {
+          TransferDirection::Download => local_path,
+          TransferDirection::Upload => remote_path,
+        };
+        log_event(window, "error", format!("Removed {} after a failed integrity check", path));
+      }
+    }
+  }
+  result
+}
+
+/// The local path a download should write to, or `None` when the existing
+/// file is kept.
+fn download_target(
+  remote: &mut dyn RemoteFs,
+  window: &Window,
+  id: &str,
+  policy: ConflictPolicy,
+  remote_path: &str,
+  local_path: &str,
+) -> Result<Option<String>, AppError> {
+  let existing = match fs::metadata(local_path) {
+    Ok(meta) if meta.is_file() => meta,
+    _ => return Ok(Some(local_path.to_string())),
+  };
+  let stat = remote.stat(remote_path).ok();
+  let conflict = Conflict {
+    id,
+    direction: TransferDirection::Download,
+    source: FileInfo {
+      path: remote_path.to_string(),
+      size: stat.as_ref().and_then(|stat| stat.size),
+      modified: stat.as_ref().and_then(|stat| stat.modified),
+    },
+    target: FileInfo {
+      path: local_path.to_string(),
+      size: Some(existing.len()),
+      modified: existing.modified().ok().and_then(system_time_millis),
+    },
+  };
+  window
+    .state::<AppState>()
+    .conflicts
+    .settle(window, policy, &conflict, &mut |candidate| Path::new(candidate).exists())
+}
+
+fn run_download(
+  session: &Session,
+  window: &Window,
+  id: &str,
+  remote_path: &str,
+  local_path: &str,
+  policy: Option<ConflictPolicy>,
+  verify: Option<bool>,
+) -> Result<(), AppError> {
+  let remote_path = session.resolve_path(remote_path);
+  let policy = conflict_policy(window, policy)?;
+  let verify = verify_enabled(window, verify)?;
+  let cancels = &window.state::<AppState>().cancels;
+  cancels.begin(id);
+  let result = session.checkout_worker(window).and_then(|mut worker| {
+    let result = download_target(worker.as_mut(), window, id, policy, &remote_path, local_path)
+      .and_then(|target| match target {
+        Some(target) => {
+          download_with(worker.as_mut(), window, id, &remote_path, &target)?;
+          if !verify {
+            return Ok(Some(None));
+          }
+          verify_after(worker.as_mut(), window, TransferDirection::Download, &target, &remote_path).map(Some)
+        }
+        None => Ok(None),
+      });
+    if result.is_ok() {
+      session.checkin_worker(worker);
+    }
+    result
+  });
+  let stop = cancels.finish(id);
   match result {
-    Ok(_) => {
-      fs::rename(&tmp_path, &local_path).map_err(map_err)?;
-      emit_done(&window, &id);
-      log_event(&window, "success", format!("Downloaded {}", remote_path));
+    Ok(Some(verified)) => {
+      emit_verified(window, id, verified);
+      log_event(window, "success", format!("Downloaded {}", remote_path));
+      Ok(())
+    }
+    Ok(None) => {
+      emit_done(window, id);
       Ok(())
     }
+    Err(_) if stop.is_some() => {
+      let paused = stop == Some(Stop::Pause);
+      emit_cancelled(window, id, paused || keep_partial(window));
+      let verb = if paused { "Paused" } else { "Cancelled" };
+      log_event(window, "info", format!("{} download of {}", verb, remote_path));
+      Err(cancel::cancelled())
+    }
     Err(err) => {
-      let _ = fs::remove_file(&tmp_path);
-      emit_error(&window, &id, err.to_string());
-      Err(map_err(err))
+      emit_error(window, id, err.clone());
+      Err(err)
     }
   }
 }
 
 #[tauri::command]
-fn upload_file(
+#[allow(clippy::too_many_arguments)]
+async fn download_file(
   state: State<'_, AppState>,
   window: Window,
+  session_id: String,
   id: String,
-  local_path: String,
   remote_path: String,
-) -> Result<(), String> {
-  let mut ftp_guard = state.ftp.lock().map_err(map_err)?;
-  let ftp = ftp_guard.as_mut().ok_or("Not connected")?;
+  local_path: String,
+  conflict_policy: Option<ConflictPolicy>,
+  verify: Option<bool>,
+) -> Result<(), AppError> {
+  let session = state.sessions.get(&session_id)?;
+  tauri::async_runtime::spawn_blocking(move || {
+    run_download(&session, &window, &id, &remote_path, &local_path, conflict_policy, verify)
+  })
+  .await
+  .map_err(map_err)?
+}
+
+/// Where to continue a partial upload this app left at `remote_path`, or
+/// `None` when the remote file is gone or larger than the local one, in which
+/// case the upload goes through the conflict policy like any other.
+fn resume_offset(remote: &mut dyn RemoteFs, local_path: &str, remote_path: &str) -> Option<u64> {
+  let local = fs::metadata(local_path).ok()?.len();
+  remote
+    .stat(remote_path)
+    .ok()
+    .filter(|stat| !stat.is_dir)
+    .and_then(|stat| stat.size)
+    .filter(|size| *size > 0 && *size <= local)
+}
 
-  let should_retry_with_epsv = |err: &FtpError| match err {
-    FtpError::ConnectionError(io_err) => {
-      io_err.kind() == std::io::ErrorKind::TimedOut
-        || io_err.raw_os_error() == Some(10060)
+/// Uploads from `offset`, which is non-zero only when continuing a partial
+/// upload. A failed upload is remembered on `session`, when given, so the
+/// next attempt can continue it.
+fn upload_with(
+  remote: &mut dyn RemoteFs,
+  session: Option<&Session>,
+  window: &Window,
+  id: &str,
+  local_path: &str,
+  remote_path: &str,
+  offset: u64,
+) -> Result<(), AppError> {
+  let mut file = File::open(local_path).map_err(map_err)?;
+  let total = file.metadata().map(|m| m.len()).ok();
+  if offset > 0 {
+    if Some(offset) == total {
+      emit_progress(window, id, offset, total);
+      return Ok(());
     }
-    FtpError::UnexpectedResponse(response) => response.status.code() == 425,
-    _ => false,
-  };
+    log_event(window, "info", format!("Resuming upload of {} at {} bytes", local_path, offset));
+    file.seek(SeekFrom::Start(offset)).map_err(map_err)?;
+  }
+  let buf_file = BufReader::with_capacity(256 * 1024, file);
+  let mut reader =
+    ProgressReader::new(buf_file, window.clone(), id.to_string(), total).starting_at(offset);
+  let result = remote.put(remote_path, offset, &mut reader).map(|_| ());
+  if result.is_err() {
+    if discard_partial(window, id) {
+      let _ = remote.rm(remote_path);
+    } else if let Some(session) = session.filter(|_| reader.transferred > offset) {
+      // The file is only read once STOR or APPE has opened the data
+      // connection, so the remote file now holds a prefix of this one.
+      session.record_partial_upload(local_path, remote_path);
+    }
+  }
+  result
+}
 
-  let mut attempt_upload = |mode: Mode| -> Result<(), FtpError> {
-    ftp.set_mode(mode);
-    let file = File::open(&local_path).map_err(FtpError::ConnectionError)?;
-    let total = file.metadata().map(|m| m.len()).ok();
-    let buf_file = BufReader::with_capacity(256 * 1024, file);
-    let reader = ProgressReader::new(buf_file, window.clone(), id.clone(), total);
-    let mut reader = reader;
-    ftp.put_file(remote_path.clone(), &mut reader).map(|_| ())
+/// The remote path an upload should write to, or `None` when the existing
+/// file is kept.
+fn upload_target(
+  remote: &mut dyn RemoteFs,
+  window: &Window,
+  id: &str,
+  policy: ConflictPolicy,
+  local_path: &str,
+  remote_path: &str,
+) -> Result<Option<String>, AppError> {
+  let existing = match remote.stat(remote_path) {
+    Ok(stat) if !stat.is_dir => stat,
+    _ => return Ok(Some(remote_path.to_string())),
+  };
+  let local = fs::metadata(local_path).ok();
+  let conflict = Conflict {
+    id,
+    direction: TransferDirection::Upload,
+    source: FileInfo {
+      path: local_path.to_string(),
+      size: local.as_ref().map(|meta| meta.len()),
+      modified: local
+        .as_ref()
+        .and_then(|meta| meta.modified().ok())
+        .and_then(system_time_millis),
+    },
+    target: FileInfo {
+      path: remote_path.to_string(),
+      size: existing.size,
+      modified: existing.modified,
+    },
   };
+  window
+    .state::<AppState>()
+    .conflicts
+    .settle(window, policy, &conflict, &mut |candidate| remote.stat(candidate).is_ok())
+}
 
-  let result = match attempt_upload(Mode::Passive) {
-    Ok(_) => Ok(()),
-    Err(err) => {
-      if should_retry_with_epsv(&err) {
-        log_event(
-          &window,
-          "info",
-          "Upload retry using EPSV (extended passive)".to_string(),
-        );
-        attempt_upload(Mode::ExtendedPassive)
-      } else {
-        Err(err)
+fn run_upload(
+  session: &Session,
+  window: &Window,
+  id: &str,
+  local_path: &str,
+  remote_path: &str,
+  policy: Option<ConflictPolicy>,
+  verify: Option<bool>,
+) -> Result<(), AppError> {
+  let remote_path = session.resolve_path(remote_path);
+  let policy = conflict_policy(window, policy)?;
+  let verify = verify_enabled(window, verify)?;
+  // A partial upload left by this app is continued, not treated as a conflict.
+  let resume = session.take_partial_upload(local_path, &remote_path);
+  let cancels = &window.state::<AppState>().cancels;
+  cancels.begin(id);
+  let result = session.checkout_worker(window).and_then(|mut worker| {
+    let offset = if resume {
+      resume_offset(worker.as_mut(), local_path, &remote_path)
+    } else {
+      None
+    };
+    let target = match offset {
+      Some(_) => Ok(Some(remote_path.clone())),
+      None => upload_target(worker.as_mut(), window, id, policy, local_path, &remote_path),
+    };
+    let result = target.and_then(|target| match target {
+      Some(target) => {
+        upload_with(worker.as_mut(), Some(session), window, id, local_path, &target, offset.unwrap_or(0))?;
+        if !verify {
+          return Ok(Some(None));
+        }
+        verify_after(worker.as_mut(), window, TransferDirection::Upload, local_path, &target).map(Some)
       }
+      None => Ok(None),
+    });
+    if result.is_ok() {
+      session.checkin_worker(worker);
     }
-  };
-
-  ftp.set_mode(Mode::Passive);
-
+    result
+  });
+  let stop = cancels.finish(id);
   match result {
-    Ok(_) => {
-      emit_done(&window, &id);
-      log_event(&window, "success", format!("Uploaded {}", local_path));
+    Ok(Some(verified)) => {
+      emit_verified(window, id, verified);
+      log_event(window, "success", format!("Uploaded {}", local_path));
+      Ok(())
+    }
+    Ok(None) => {
+      emit_done(window, id);
       Ok(())
     }
+    Err(_) if stop.is_some() => {
+      let paused = stop == Some(Stop::Pause);
+      emit_cancelled(window, id, paused || keep_partial(window));
+      let verb = if paused { "Paused" } else { "Cancelled" };
+      log_event(window, "info", format!("{} upload of {}", verb, local_path));
+      Err(cancel::cancelled())
+    }
     Err(err) => {
-      emit_error(&window, &id, err.to_string());
-      Err(map_err(err))
+      emit_error(window, id, err.clone());
+      Err(err)
+    }
+  }
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn upload_file(
+  state: State<'_, AppState>,
+  window: Window,
+  session_id: String,
+  id: String,
+  local_path: String,
+  remote_path: String,
+  conflict_policy: Option<ConflictPolicy>,
+  verify: Option<bool>,
+) -> Result<(), AppError> {
+  let session = state.sessions.get(&session_id)?;
+  tauri::async_runtime::spawn_blocking(move || {
+    run_upload(&session, &window, &id, &local_path, &remote_path, conflict_policy, verify)
+  })
+  .await
+  .map_err(map_err)?
+}
+
+/// Returns the problems logged during startup, each only once.
+#[tauri::command]
+fn take_startup_log(state: State<'_, AppState>) -> Vec<LogEntry> {
+  state.startup_log.take()
+}
+
+/// Answers a `transfer-conflict` prompt raised by the ask policy.
+#[tauri::command]
+fn resolve_conflict(state: State<'_, AppState>, id: String, decision: ConflictDecision) -> Result<(), AppError> {
+  state.conflicts.resolve(&id, decision)
+}
+
+#[tauri::command]
+fn queue_enqueue(
+  state: State<'_, AppState>,
+  session_id: String,
+  jobs: Vec<EnqueueRequest>,
+) -> Result<Vec<String>, AppError> {
+  let session = state.sessions.get(&session_id)?;
+  state.queue.enqueue(&session_id, &session, jobs)
+}
+
+#[tauri::command]
+fn queue_list(state: State<'_, AppState>) -> Result<QueueSnapshot, AppError> {
+  state.queue.snapshot()
+}
+
+#[tauri::command]
+fn queue_pause(state: State<'_, AppState>, ids: Option<Vec<String>>) -> Result<Vec<String>, AppError> {
+  let paused = state.queue.pause(ids, &state.cancels)?;
+  state.conflicts.wake();
+  Ok(paused)
+}
+
+#[tauri::command]
+fn queue_resume(state: State<'_, AppState>, ids: Option<Vec<String>>) -> Result<Vec<String>, AppError> {
+  state.queue.resume(ids)
+}
+
+#[tauri::command]
+fn queue_cancel(state: State<'_, AppState>, ids: Option<Vec<String>>) -> Result<Vec<String>, AppError> {
+  let cancelled = state.queue.cancel(ids, &state.cancels)?;
+  state.conflicts.wake();
+  Ok(cancelled)
+}
+
+/// Stops a running download, upload or copy, or a queued job before it
+/// starts. Returns whether anything by that id was found.
+#[tauri::command]
+fn cancel_transfer(state: State<'_, AppState>, id: String) -> Result<bool, AppError> {
+  let found = state.cancels.cancel(&id) || !state.queue.cancel(Some(vec![id]), &state.cancels)?.is_empty();
+  state.conflicts.wake();
+  Ok(found)
+}
+
+#[tauri::command]
+fn queue_reprioritize(state: State<'_, AppState>, id: String, position: usize) -> Result<(), AppError> {
+  state.queue.reprioritize(&id, position)
+}
+
+#[tauri::command]
+fn queue_set_concurrency(state: State<'_, AppState>, concurrency: usize) -> Result<(), AppError> {
+  state.queue.set_concurrency(concurrency)?;
+  state
+    .prefs
+    .update(|prefs| prefs.transfers.concurrency = concurrency)?;
+  Ok(())
+}
+
+#[tauri::command]
+fn queue_clear_finished(state: State<'_, AppState>) -> Result<(), AppError> {
+  state.queue.clear_finished()
+}
+
+#[tauri::command]
+fn site_list(state: State<'_, AppState>) -> Result<Vec<SiteProfile>, AppError> {
+  state.sites.list()
+}
+
+#[tauri::command]
+fn site_add(state: State<'_, AppState>, site: SiteProfile) -> Result<SiteProfile, AppError> {
+  state.sites.add(site)
+}
+
+#[tauri::command]
+fn site_update(state: State<'_, AppState>, site: SiteProfile) -> Result<(), AppError> {
+  state.sites.update(site)
+}
+
+#[tauri::command]
+fn site_delete(state: State<'_, AppState>, id: String) -> Result<(), AppError> {
+  let site = state.sites.get(&id)?;
+  state.sites.delete(&id)?;
+  if let Some(reference) = site.password_ref {
+    let _ = state.vault.delete(&reference);
+  }
+  Ok(())
+}
+
+/// Saves or, with `None`, forgets the password of a site.
+#[tauri::command]
+fn site_set_password(
+  state: State<'_, AppState>,
+  id: String,
+  password: Option<String>,
+) -> Result<SiteProfile, AppError> {
+  let mut site = state.sites.get(&id)?;
+  match password {
+    Some(password) => {
+      site.password_ref = Some(state.vault.store(&password, site.password_ref.as_deref())?);
+    }
+    None => {
+      if let Some(reference) = site.password_ref.take() {
+        state.vault.delete(&reference)?;
+      }
+    }
+  }
+  state.sites.update(site.clone())?;
+  Ok(site)
+}
+
+#[tauri::command]
+fn vault_status(state: State<'_, AppState>) -> Result<VaultStatus, AppError> {
+  state.vault.status()
+}
+
+#[tauri::command]
+fn vault_unlock(state: State<'_, AppState>, master_password: String) -> Result<(), AppError> {
+  state.vault.unlock(&master_password)
+}
+
+#[tauri::command]
+fn vault_lock(state: State<'_, AppState>) -> Result<(), AppError> {
+  state.vault.lock_vault()
+}
+
+#[tauri::command]
+fn vault_store(
+  state: State<'_, AppState>,
+  secret: String,
+  reference: Option<String>,
+) -> Result<String, AppError> {
+  state.vault.store(&secret, reference.as_deref())
+}
+
+#[tauri::command]
+fn vault_delete(state: State<'_, AppState>, reference: String) -> Result<(), AppError> {
+  state.vault.delete(&reference)
+}
+
+/// Imports an exported sites file, FileZilla `sitemanager.xml` or
+/// `WinSCP.ini`. Recovered passwords go to the vault; if it is locked the
+/// sites are still imported without them.
+#[tauri::command]
+fn import_sites(state: State<'_, AppState>, path: String) -> Result<ImportReport, AppError> {
+  let parsed = parse_import_file(Path::new(&path))?;
+  let mut warnings = parsed.warnings;
+  let mut vault_error = None;
+  let mut profiles = Vec::new();
+  for mut site in parsed.sites {
+    if let (Some(password), None) = (site.password.as_deref(), vault_error.as_ref()) {
+      match state.vault.store(password, None) {
+        Ok(reference) => site.profile.password_ref = Some(reference),
+        Err(err) => vault_error = Some(err),
+      }
     }
+    profiles.push(site.profile);
+  }
+  if let Some(err) = vault_error {
+    warnings.push(format!("Passwords were not saved: {}", err));
   }
+
+  let candidates: Vec<Option<String>> = profiles.iter().map(|site| site.password_ref.clone()).collect();
+  let sites = state.sites.merge(profiles)?;
+  // Secrets of sites skipped as duplicates are not referenced by anything.
+  for reference in candidates.into_iter().flatten() {
+    if !sites.iter().any(|site| site.password_ref.as_ref() == Some(&reference)) {
+      let _ = state.vault.delete(&reference);
+    }
+  }
+  Ok(ImportReport { sites, warnings })
+}
+
+#[tauri::command]
+fn export_sites(state: State<'_, AppState>, path: String, ids: Option<Vec<String>>) -> Result<usize, AppError> {
+  state.sites.export(Path::new(&path), ids)
+}
+
+#[tauri::command]
+fn watch_start(
+  state: State<'_, AppState>,
+  app: AppHandle,
+  session_id: String,
+  local_dir: String,
+  remote_dir: String,
+) -> Result<WatchInfo, AppError> {
+  let session = state.sessions.get(&session_id)?;
+  let remote_dir = session.resolve_path(&remote_dir);
+  state.watches.start(app, session.key(), local_dir, remote_dir)
+}
+
+#[tauri::command]
+fn watch_stop(state: State<'_, AppState>, id: String) -> Result<(), AppError> {
+  state.watches.stop(&id)
+}
+
+#[tauri::command]
+fn watch_list(state: State<'_, AppState>) -> Result<Vec<WatchInfo>, AppError> {
+  state.watches.list()
 }
 
 #[tauri::command]
-fn list_local(path: String) -> Result<LocalListResponse, String> {
+fn list_local(path: String) -> Result<LocalListResponse, AppError> {
   let trimmed = path.trim();
   if trimmed.is_empty() || trimmed.eq_ignore_ascii_case("this_pc") {
     let mut entries = Vec::new();
@@ -955,13 +1738,13 @@ fn list_local(path: String) -> Result<LocalListResponse, String> {
 }
 
 #[tauri::command]
-fn create_local_dir(path: String) -> Result<(), String> {
+fn create_local_dir(path: String) -> Result<(), AppError> {
   fs::create_dir_all(path).map_err(map_err)?;
   Ok(())
 }
 
 #[tauri::command]
-fn create_local_file(path: String) -> Result<(), String> {
+fn create_local_file(path: String) -> Result<(), AppError> {
   if let Some(parent) = Path::new(&path).parent() {
     fs::create_dir_all(parent).map_err(map_err)?;
   }
@@ -969,7 +1752,7 @@ fn create_local_file(path: String) -> Result<(), String> {
   Ok(())
 }
 
-fn copy_dir_all(from: &Path, to: &Path) -> Result<(), String> {
+fn copy_dir_all(from: &Path, to: &Path) -> Result<(), AppError> {
   fs::create_dir_all(to).map_err(map_err)?;
   for entry in fs::read_dir(from).map_err(map_err)? {
     let entry = entry.map_err(map_err)?;
@@ -985,7 +1768,7 @@ fn copy_dir_all(from: &Path, to: &Path) -> Result<(), String> {
 }
 
 #[tauri::command]
-fn copy_local(from: String, to: String) -> Result<(), String> {
+fn copy_local(from: String, to: String) -> Result<(), AppError> {
   let from_path = Path::new(&from);
   let to_path = Path::new(&to);
   if from_path.is_dir() {
@@ -1000,7 +1783,7 @@ fn copy_local(from: String, to: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-fn delete_local(path: String, is_dir: bool) -> Result<(), String> {
+fn delete_local(path: String, is_dir: bool) -> Result<(), AppError> {
   if is_dir {
     fs::remove_dir_all(path).map_err(map_err)?;
   } else {
@@ -1010,7 +1793,7 @@ fn delete_local(path: String, is_dir: bool) -> Result<(), String> {
 }
 
 #[tauri::command]
-fn rename_local(from: String, to: String) -> Result<(), String> {
+fn rename_local(from: String, to: String) -> Result<(), AppError> {
   fs::rename(from, to).map_err(map_err)?;
   Ok(())
 }
@@ -1021,10 +1804,10 @@ fn path_exists(path: String) -> bool {
 }
 
 #[tauri::command]
-fn launch_path(path: String) -> Result<(), String> {
+fn launch_path(path: String) -> Result<(), AppError> {
   let target = Path::new(&path);
   if !target.exists() {
-    return Err("File not found.".to_string());
+    return Err("File not found.".into());
   }
   #[cfg(target_os = "windows")]
   {
@@ -1063,11 +1846,11 @@ fn launch_path(path: String) -> Result<(), String> {
     return Ok(());
   }
   #[allow(unreachable_code)]
-  Err("Unsupported platform.".to_string())
+  Err("Unsupported platform.".into())
 }
 
 #[tauri::command]
-fn open_with_dialog(path: String) -> Result<(), String> {
+fn open_with_dialog(path: String) -> Result<(), AppError> {
   #[cfg(target_os = "windows")]
   {
     std::process::Command::new("rundll32.exe")
@@ -1079,16 +1862,16 @@ fn open_with_dialog(path: String) -> Result<(), String> {
   #[cfg(not(target_os = "windows"))]
   {
     let _ = path;
-    Err("Open with dialog is only supported on Windows.".to_string())
+    Err("Open with dialog is only supported on Windows.".into())
   }
 }
 
 #[tauri::command]
-fn open_properties(path: String) -> Result<(), String> {
+fn open_properties(path: String) -> Result<(), AppError> {
   #[cfg(target_os = "windows")]
   {
     if !Path::new(&path).exists() {
-      return Err("File not found.".to_string());
+      return Err("File not found.".into());
     }
     let parent = Path::new(&path)
       .parent()
@@ -1113,7 +1896,7 @@ fn open_properties(path: String) -> Result<(), String> {
   #[cfg(not(target_os = "windows"))]
   {
     let _ = path;
-    Err("Properties dialog is only supported on Windows.".to_string())
+    Err("Properties dialog is only supported on Windows.".into())
   }
 }
 
@@ -1164,11 +1947,11 @@ fn mime_from_path(path: &str) -> &'static str {
 }
 
 #[tauri::command]
-fn read_local_image_data(path: String) -> Result<String, String> {
+fn read_local_image_data(path: String) -> Result<String, AppError> {
   let metadata = fs::metadata(&path).map_err(map_err)?;
   const MAX_IMAGE_BYTES: u64 = 100 * 1024 * 1024;
   if metadata.len() > MAX_IMAGE_BYTES {
-    return Err("too_large".to_string());
+    return Err(AppError::new(ErrorKind::TooLarge, "File is too large to preview"));
   }
   let mut file = File::open(&path).map_err(map_err)?;
   let mut buf = Vec::new();
@@ -1179,7 +1962,7 @@ fn read_local_image_data(path: String) -> Result<String, String> {
 }
 
 #[tauri::command]
-fn read_local_image_thumb(path: String, max_size: u32) -> Result<String, String> {
+fn read_local_image_thumb(path: String, max_size: u32) -> Result<String, AppError> {
   let img = image::open(&path).map_err(map_err)?;
   let resized = img.resize(max_size, max_size, image::imageops::FilterType::Triangle);
   let mut out = Vec::new();
@@ -1190,10 +1973,10 @@ fn read_local_image_thumb(path: String, max_size: u32) -> Result<String, String>
 }
 
 #[tauri::command]
-fn read_local_video_data(path: String, max_bytes: u64) -> Result<String, String> {
+fn read_local_video_data(path: String, max_bytes: u64) -> Result<String, AppError> {
   let metadata = fs::metadata(&path).map_err(map_err)?;
   if metadata.len() > max_bytes {
-    return Err("too_large".to_string());
+    return Err(AppError::new(ErrorKind::TooLarge, "File is too large to preview"));
   }
   let mut file = File::open(&path).map_err(map_err)?;
   let mut buf = Vec::new();
@@ -1204,7 +1987,7 @@ fn read_local_video_data(path: String, max_bytes: u64) -> Result<String, String>
 }
 
 #[tauri::command]
-fn read_local_video_thumb(_window: Window, path: String, max_size: u32) -> Result<String, String> {
+fn read_local_video_thumb(_window: Window, path: String, max_size: u32) -> Result<String, AppError> {
   #[cfg(target_os = "windows")]
   {
     return read_shell_thumbnail(&path, max_size);
@@ -1213,12 +1996,12 @@ fn read_local_video_thumb(_window: Window, path: String, max_size: u32) -> Resul
   {
     let _ = path;
     let _ = max_size;
-    return Err("unsupported_platform".to_string());
+    return Err(AppError::new(ErrorKind::Unsupported, "Video thumbnails are only available on Windows"));
   }
 }
 
 #[cfg(target_os = "windows")]
-fn read_shell_thumbnail(path: &str, max_size: u32) -> Result<String, String> {
+fn read_shell_thumbnail(path: &str, max_size: u32) -> Result<String, AppError> {
   let com_needs_uninit = unsafe {
     CoInitializeEx(None, COINIT_APARTMENTTHREADED).is_ok()
   };
@@ -1226,7 +2009,7 @@ fn read_shell_thumbnail(path: &str, max_size: u32) -> Result<String, String> {
   let wide: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
   if max_size > i32::MAX as u32 {
     if com_needs_uninit { unsafe { CoUninitialize() }; }
-    return Err("Thumbnail size too large".to_string());
+    return Err("Thumbnail size too large".into());
   }
   let size = SIZE {
     cx: max_size as i32,
@@ -1263,7 +2046,7 @@ fn read_shell_thumbnail(path: &str, max_size: u32) -> Result<String, String> {
   if res == 0 {
     unsafe { DeleteObject(hbitmap) };
     if com_needs_uninit { unsafe { CoUninitialize() }; }
-    return Err("bitmap_failed".to_string());
+    return Err("bitmap_failed".into());
   }
 
   let width = bmp.bmWidth.max(1) as i32;
@@ -1291,7 +2074,7 @@ fn read_shell_thumbnail(path: &str, max_size: u32) -> Result<String, String> {
   if hdc.0 == 0 {
     unsafe { DeleteObject(hbitmap) };
     if com_needs_uninit { unsafe { CoUninitialize() }; }
-    return Err("dc_failed".to_string());
+    return Err("dc_failed".into());
   }
   let old = unsafe { SelectObject(hdc, hbitmap) };
   let scan = unsafe {
@@ -1311,7 +2094,7 @@ fn read_shell_thumbnail(path: &str, max_size: u32) -> Result<String, String> {
   if com_needs_uninit { unsafe { CoUninitialize() }; }
 
   if scan == 0 {
-    return Err("dibits_failed".to_string());
+    return Err("dibits_failed".into());
   }
 
   let mut rgba = Vec::with_capacity(buf.len());
@@ -1332,43 +2115,81 @@ fn read_shell_thumbnail(path: &str, max_size: u32) -> Result<String, String> {
 }
 
 #[tauri::command]
-fn update_preferences(state: State<'_, AppState>, prefs: UiPreferencesInput) -> Result<(), String> {
-  let mut guard = state.prefs.lock().map_err(map_err)?;
-  guard.open_on_startup = prefs.open_on_startup;
-  guard.close_to_tray = prefs.close_to_tray;
-  guard.minimize_to_tray = prefs.minimize_to_tray;
-  set_autostart(prefs.open_on_startup)?;
+fn update_preferences(state: State<'_, AppState>, prefs: PreferencesUpdate) -> Result<Preferences, AppError> {
+  if let Some(enable) = prefs.open_on_startup {
+    set_autostart(enable)?;
+  }
+  let updated = state.prefs.update(|current| {
+    if let Some(value) = prefs.open_on_startup {
+      current.open_on_startup = value;
+    }
+    if let Some(value) = prefs.close_to_tray {
+      current.close_to_tray = value;
+    }
+    if let Some(value) = prefs.minimize_to_tray {
+      current.minimize_to_tray = value;
+    }
+    if let Some(transfers) = prefs.transfers {
+      current.transfers = transfers;
+    }
+  })?;
+  state.queue.set_concurrency(updated.transfers.concurrency)?;
+  Ok(updated)
+}
+
+#[tauri::command]
+fn get_preferences(state: State<'_, AppState>) -> Result<Preferences, AppError> {
+  state.prefs.get()
+}
+
+#[tauri::command]
+fn get_throttle(state: State<'_, AppState>) -> Result<ThrottleConfig, AppError> {
+  state.throttle.config()
+}
+
+/// Changes rate limits for running and future transfers and saves them.
+#[tauri::command]
+fn set_throttle(state: State<'_, AppState>, config: ThrottleConfig) -> Result<(), AppError> {
+  state.throttle.set_config(config.clone())?;
+  state.prefs.update(|prefs| prefs.throttle = config)?;
   Ok(())
 }
 
+#[tauri::command]
+fn get_autostart_status() -> Result<AutostartStatus, AppError> {
+  autostart_status()
+}
+
 /* ── Recursive file listing for folder transfers ── */
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 struct RecursiveEntry {
   relative_path: String,
   is_dir: bool,
   size: Option<u64>,
+  /// Milliseconds since the Unix epoch.
+  modified: Option<i64>,
 }
 
-#[tauri::command]
-fn list_local_files_recursive(root: String) -> Result<Vec<RecursiveEntry>, String> {
-  let root_path = Path::new(&root);
+fn walk_local(root: &str) -> Result<Vec<RecursiveEntry>, AppError> {
+  let root_path = Path::new(root);
   if !root_path.is_dir() {
-    return Err("Path is not a directory".to_string());
+    return Err("Path is not a directory".into());
   }
   let mut results = Vec::new();
-  fn walk(dir: &Path, base: &Path, out: &mut Vec<RecursiveEntry>) -> Result<(), String> {
+  fn walk(dir: &Path, base: &Path, out: &mut Vec<RecursiveEntry>) -> Result<(), AppError> {
     let entries = fs::read_dir(dir).map_err(map_err)?;
     for entry in entries {
       let entry = entry.map_err(map_err)?;
       let path = entry.path();
       let rel = path.strip_prefix(base).map_err(|e| e.to_string())?.to_string_lossy().replace('\\', "/");
       let meta = entry.metadata().map_err(map_err)?;
+      let modified = meta.modified().ok().and_then(system_time_millis);
       if meta.is_dir() {
-        out.push(RecursiveEntry { relative_path: rel.clone(), is_dir: true, size: None });
+        out.push(RecursiveEntry { relative_path: rel.clone(), is_dir: true, size: None, modified });
         walk(&path, base, out)?;
       } else {
-        out.push(RecursiveEntry { relative_path: rel, is_dir: false, size: Some(meta.len()) });
+        out.push(RecursiveEntry { relative_path: rel, is_dir: false, size: Some(meta.len()), modified });
       }
     }
     Ok(())
@@ -1378,18 +2199,16 @@ fn list_local_files_recursive(root: String) -> Result<Vec<RecursiveEntry>, Strin
 }
 
 #[tauri::command]
-fn list_remote_files_recursive(
-  state: State<'_, AppState>,
-  path: String,
-) -> Result<Vec<RecursiveEntry>, String> {
-  let mut ftp_guard = state.ftp.lock().map_err(map_err)?;
-  let ftp = ftp_guard.as_mut().ok_or("Not connected")?;
+fn list_local_files_recursive(root: String) -> Result<Vec<RecursiveEntry>, AppError> {
+  walk_local(&root)
+}
+
+fn walk_remote(remote: &mut dyn RemoteFs, path: &str) -> Result<Vec<RecursiveEntry>, AppError> {
   let mut results = Vec::new();
-  fn walk(ftp: &mut FtpStream, dir: &str, base: &str, out: &mut Vec<RecursiveEntry>) -> Result<(), String> {
-    let listing = ftp.list(Some(dir)).map_err(map_err)?;
-    let entries = parse_list_entries(listing);
+  fn walk(remote: &mut dyn RemoteFs, dir: &str, base: &str, out: &mut Vec<RecursiveEntry>) -> Result<(), AppError> {
+    let entries = remote.list(Some(dir))?;
     for entry in entries {
-      if entry.name == "." || entry.name == ".." { continue; }
+      if entry.name == "." || entry.name == ".." || entry.is_symlink { continue; }
       let full = join_remote(dir, &entry.name);
       let rel = if base.ends_with('/') {
         full.strip_prefix(base).unwrap_or(&full).to_string()
@@ -1397,24 +2216,116 @@ fn list_remote_files_recursive(
         full.strip_prefix(&format!("{}/", base)).unwrap_or(&full).to_string()
       };
       if entry.is_dir {
-        out.push(RecursiveEntry { relative_path: rel.clone(), is_dir: true, size: None });
-        walk(ftp, &full, base, out)?;
+        out.push(RecursiveEntry { relative_path: rel.clone(), is_dir: true, size: None, modified: entry.modified });
+        walk(remote, &full, base, out)?;
       } else {
-        out.push(RecursiveEntry { relative_path: rel, is_dir: false, size: entry.size });
+        out.push(RecursiveEntry { relative_path: rel, is_dir: false, size: entry.size, modified: entry.modified });
       }
     }
     Ok(())
   }
-  walk(ftp, &path, &path, &mut results)?;
+  walk(remote, path, path, &mut results)?;
   Ok(results)
 }
 
+#[tauri::command]
+fn list_remote_files_recursive(
+  state: State<'_, AppState>,
+  window: Window,
+  session_id: String,
+  path: String,
+) -> Result<Vec<RecursiveEntry>, AppError> {
+  let session = state.sessions.get(&session_id)?;
+  session.with_remote(&window, true, |remote| walk_remote(remote, &path))
+}
+
+/// Dry run: compares the trees and returns the plan without changing
+/// anything.
+#[tauri::command]
+async fn sync_plan(
+  state: State<'_, AppState>,
+  window: Window,
+  session_id: String,
+  options: SyncOptions,
+) -> Result<SyncPlan, AppError> {
+  let session = state.sessions.get(&session_id)?;
+  tauri::async_runtime::spawn_blocking(move || {
+    let mut worker = session.checkout_worker(&window)?;
+    let plan = sync::plan_sync(worker.as_mut(), &options)?;
+    session.checkin_worker(worker);
+    Ok(plan)
+  })
+  .await
+  .map_err(map_err)?
+}
+
+#[tauri::command]
+async fn sync_execute(
+  state: State<'_, AppState>,
+  window: Window,
+  session_id: String,
+  id: String,
+  plan: SyncPlan,
+) -> Result<SyncReport, AppError> {
+  let session = state.sessions.get(&session_id)?;
+  // Registered so `cancel_transfer` can stop the run.
+  state.cancels.begin(&id);
+  tauri::async_runtime::spawn_blocking(move || {
+    let report = sync::execute_sync(&session, &window, &id, &plan);
+    let state = window.state::<AppState>();
+    state.cancels.finish(&id);
+    state.progress.finish(&id);
+    Ok(report)
+  })
+  .await
+  .map_err(map_err)?
+}
+
 fn main() {
+  let context = tauri::generate_context!();
+  let (prefs, problem) = Settings::load(tauri::api::path::app_config_dir(context.config()));
+  let throttle = Throttle::new(prefs.get().map(|prefs| prefs.throttle).unwrap_or_default());
+  let startup_log = StartupLog::default();
+  if let Some(problem) = problem {
+    startup_log.error(problem);
+  }
+  let state = AppState {
+    prefs,
+    throttle,
+    startup_log,
+    ..Default::default()
+  };
   let builder = tauri::Builder::default()
-    .manage(AppState::default())
+    .manage(state)
     .setup(|app| {
       #[cfg(target_os = "windows")]
       apply_window_icon(app);
+      if let Some(dir) = app.path_resolver().app_data_dir() {
+        if let Err(err) = app.state::<AppState>().queue.load(dir) {
+          app
+            .state::<AppState>()
+            .startup_log
+            .error(format!("Failed to restore transfer queue: {}", err));
+        }
+      }
+      if let Ok(prefs) = app.state::<AppState>().prefs.get() {
+        let _ = app.state::<AppState>().queue.set_concurrency(prefs.transfers.concurrency);
+      }
+      if let Some(dir) = app.path_resolver().app_config_dir() {
+        if let Err(err) = app.state::<AppState>().sites.load(dir.clone()) {
+          app
+            .state::<AppState>()
+            .startup_log
+            .error(format!("Failed to load saved sites: {}", err));
+        }
+        if let Err(err) = app.state::<AppState>().vault.load(dir) {
+          app
+            .state::<AppState>()
+            .startup_log
+            .error(format!("Failed to load credential vault: {}", err));
+        }
+      }
+      TransferQueue::start(app.handle());
       if cfg!(debug_assertions) {
         if let Some(window) = app.get_window("main") {
           let _ = window.eval("window.location.replace('http://localhost:1420/');");
@@ -1474,7 +2385,7 @@ fn main() {
       return;
     }
     if let Some(state) = window.try_state::<AppState>() {
-      if let Ok(prefs) = state.prefs.lock() {
+      if let Ok(prefs) = state.prefs.get() {
         match event.event() {
           #[cfg(feature = "system-tray")]
           tauri::WindowEvent::CloseRequested { api, .. } => {
@@ -1511,6 +2422,32 @@ fn main() {
       rename_path,
       download_file,
       upload_file,
+      resolve_conflict,
+      queue_enqueue,
+      queue_list,
+      take_startup_log,
+      queue_pause,
+      queue_resume,
+      queue_cancel,
+      cancel_transfer,
+      queue_reprioritize,
+      queue_set_concurrency,
+      queue_clear_finished,
+      site_list,
+      site_add,
+      site_update,
+      site_delete,
+      site_set_password,
+      vault_status,
+      vault_unlock,
+      vault_lock,
+      vault_store,
+      vault_delete,
+      import_sites,
+      export_sites,
+      watch_start,
+      watch_stop,
+      watch_list,
       list_local,
       create_local_dir,
       create_local_file,
@@ -1530,9 +2467,15 @@ fn main() {
       copy_remote,
       create_remote_file,
       update_preferences,
+      get_preferences,
+      get_autostart_status,
+      get_throttle,
+      set_throttle,
       list_local_files_recursive,
-      list_remote_files_recursive
+      list_remote_files_recursive,
+      sync_plan,
+      sync_execute
     ])
-    .run(tauri::generate_context!())
+    .run(context)
     .expect("error while running tauri application");
 }
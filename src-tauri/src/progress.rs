@@ -0,0 +1,183 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long throughput is sampled before it feeds the smoothed rate, so
+/// bursts of small reads do not make the speed jump around.
+const SAMPLE_WINDOW: Duration = Duration::from_millis(500);
+/// Weight of the newest sample in the smoothed rate.
+const SMOOTHING: f64 = 0.3;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TransferProgress {
+  pub id: String,
+  pub transferred: u64,
+  pub total: Option<u64>,
+  #[serde(flatten)]
+  pub rates: Rates,
+}
+
+/// Files and bytes across a directory transfer, sync run or queued batch.
+#[derive(Clone, Debug, Serialize)]
+pub struct BatchProgress {
+  pub id: String,
+  pub files_done: usize,
+  pub files_total: usize,
+  pub bytes_done: u64,
+  pub bytes_total: Option<u64>,
+  #[serde(flatten)]
+  pub rates: Rates,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Rates {
+  /// Recent throughput, smoothed over the last few seconds.
+  pub bytes_per_sec: u64,
+  /// Throughput since the transfer started.
+  pub average_bytes_per_sec: u64,
+  /// Unknown until both the total and a rate are.
+  pub eta_secs: Option<u64>,
+  pub elapsed_ms: u64,
+}
+
+struct Meter {
+  started: Instant,
+  last_bytes: u64,
+  /// Bytes moved since the first sample; resumed prefixes do not count.
+  moved: u64,
+  window_started: Instant,
+  window_bytes: u64,
+  speed: Option<f64>,
+}
+
+impl Meter {
+  fn new(bytes: u64) -> Self {
+    let now = Instant::now();
+    Self {
+      started: now,
+      last_bytes: bytes,
+      moved: 0,
+      window_started: now,
+      window_bytes: 0,
+      speed: None,
+    }
+  }
+
+  /// `per_file` counts restart at zero for every file of a batch, so a lower
+  /// count means the next file began rather than that bytes were lost.
+  fn advance(&mut self, bytes: u64, per_file: bool) {
+    let delta = match bytes.checked_sub(self.last_bytes) {
+      Some(delta) => delta,
+      None if per_file => bytes,
+      None => 0,
+    };
+    self.last_bytes = bytes;
+    self.moved += delta;
+    self.window_bytes += delta;
+    let window = self.window_started.elapsed();
+    if window >= SAMPLE_WINDOW {
+      let sample = self.window_bytes as f64 / window.as_secs_f64();
+      self.speed = Some(match self.speed {
+        Some(speed) => speed + SMOOTHING * (sample - speed),
+        None => sample,
+      });
+      self.window_started = Instant::now();
+      self.window_bytes = 0;
+    }
+  }
+
+  fn rates(&self, remaining: Option<u64>) -> Rates {
+    let elapsed = self.started.elapsed();
+    let average = if elapsed.is_zero() {
+      0.0
+    } else {
+      self.moved as f64 / elapsed.as_secs_f64()
+    };
+    let speed = self.speed.unwrap_or(average);
+    let eta_secs = match remaining {
+      Some(0) => Some(0),
+      Some(remaining) if speed >= 1.0 => Some((remaining as f64 / speed).ceil() as u64),
+      _ => None,
+    };
+    Rates {
+      bytes_per_sec: speed as u64,
+      average_bytes_per_sec: average as u64,
+      eta_secs,
+      elapsed_ms: elapsed.as_millis() as u64,
+    }
+  }
+}
+
+/// Keeps throughput history per transfer and per batch between progress
+/// events.
+#[derive(Default)]
+pub struct ProgressTracker {
+  transfers: Mutex<HashMap<String, Meter>>,
+  batches: Mutex<HashMap<String, Meter>>,
+}
+
+fn sample(
+  meters: &Mutex<HashMap<String, Meter>>,
+  id: &str,
+  done: u64,
+  total: Option<u64>,
+  per_file: bool,
+) -> Rates {
+  let mut meters = match meters.lock() {
+    Ok(meters) => meters,
+    Err(_) => return Rates::default(),
+  };
+  let meter = meters.entry(id.to_string()).or_insert_with(|| Meter::new(done));
+  meter.advance(done, per_file);
+  meter.rates(total.map(|total| total.saturating_sub(done)))
+}
+
+impl ProgressTracker {
+  pub fn transfer(&self, id: &str, transferred: u64, total: Option<u64>) -> TransferProgress {
+    TransferProgress {
+      id: id.to_string(),
+      transferred,
+      total,
+      rates: sample(&self.transfers, id, transferred, total, true),
+    }
+  }
+
+  /// Bytes the transfer has reported so far, if it is running.
+  pub fn transferred(&self, id: &str) -> Option<u64> {
+    let transfers = self.transfers.lock().ok()?;
+    transfers.get(id).map(|meter| meter.last_bytes)
+  }
+
+  pub fn finish(&self, id: &str) {
+    if let Ok(mut transfers) = self.transfers.lock() {
+      transfers.remove(id);
+    }
+  }
+
+  /// Counts are up to the caller; the batch is forgotten once every file is
+  /// done.
+  pub fn batch(
+    &self,
+    id: &str,
+    files_done: usize,
+    files_total: usize,
+    bytes_done: u64,
+    bytes_total: Option<u64>,
+  ) -> BatchProgress {
+    let rates = sample(&self.batches, id, bytes_done, bytes_total, false);
+    if files_done >= files_total {
+      if let Ok(mut batches) = self.batches.lock() {
+        batches.remove(id);
+      }
+    }
+    BatchProgress {
+      id: id.to_string(),
+      files_done,
+      files_total,
+      bytes_done,
+      bytes_total,
+      rates,
+    }
+  }
+}
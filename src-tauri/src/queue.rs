@@ -0,0 +1,517 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::cancel::Cancellations;
+use crate::error::{AppError, ErrorKind};
+use crate::progress::{BatchProgress, ProgressTracker};
+use crate::session::Session;
+use crate::settings::ConflictPolicy;
+use crate::{map_err, now_millis, run_download, run_upload, AppState};
+
+const QUEUE_FILE: &str = "transfer-queue.json";
+const QUEUE_VERSION: u32 = 1;
+const DEFAULT_CONCURRENCY: usize = 2;
+const MAX_CONCURRENCY: usize = 16;
+/// Bounds how often a busy queue is rewritten to disk.
+const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferDirection {
+  Upload,
+  Download,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+  Queued,
+  Paused,
+  Active,
+  Done,
+  Failed,
+  Cancelled,
+}
+
+/// Serialized in camelCase like `EnqueueRequest`; the aliases read queue files
+/// saved with the earlier snake_case names.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferJob {
+  pub id: String,
+  pub direction: TransferDirection,
+  #[serde(alias = "local_path")]
+  pub local_path: String,
+  #[serde(alias = "remote_path")]
+  pub remote_path: String,
+  /// Identifies the server independently of the session id, so jobs restored
+  /// after a restart run once the user reconnects to the same server.
+  #[serde(alias = "session_key")]
+  pub session_key: String,
+  #[serde(skip)]
+  pub session_id: Option<String>,
+  pub status: JobStatus,
+  pub error: Option<String>,
+  pub created: i64,
+  /// Falls back to the default from the settings when unset.
+  #[serde(default, alias = "conflict_policy")]
+  pub conflict_policy: Option<ConflictPolicy>,
+  /// Shared by jobs enqueued together, which report `batch-progress`.
+  #[serde(default)]
+  pub batch: Option<String>,
+  #[serde(default)]
+  pub size: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnqueueRequest {
+  id: Option<String>,
+  direction: TransferDirection,
+  local_path: String,
+  remote_path: String,
+  #[serde(default)]
+  conflict_policy: Option<ConflictPolicy>,
+  /// Known from the listing; local files are measured when it is missing.
+  #[serde(default)]
+  size: Option<u64>,
+}
+
+impl EnqueueRequest {
+  pub fn new(direction: TransferDirection, local_path: String, remote_path: String) -> Self {
+    Self {
+      id: None,
+      direction,
+      local_path,
+      remote_path,
+      conflict_policy: None,
+      size: None,
+    }
+  }
+
+  pub fn with_conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+    self.conflict_policy = Some(policy);
+    self
+  }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct QueueSnapshot {
+  concurrency: usize,
+  jobs: Vec<TransferJob>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct JobUpdate {
+  id: String,
+  status: JobStatus,
+  error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct QueueFile {
+  version: u32,
+  concurrency: usize,
+  jobs: Vec<TransferJob>,
+}
+
+struct QueueInner {
+  jobs: Vec<TransferJob>,
+  concurrency: usize,
+  active: usize,
+  dirty: bool,
+  next_id: u64,
+  /// Running jobs asked to pause, which go back to `Paused` when they stop.
+  pausing: BTreeSet<String>,
+}
+
+/// Rust-side transfer queue. Jobs run in list order, up to `concurrency` at a
+/// time, each on its own worker connection. Pause and cancel both stop
+/// running jobs at the next chunk; a paused job keeps its partial file and
+/// continues from it when resumed.
+pub struct TransferQueue {
+  inner: Mutex<QueueInner>,
+  wake: Condvar,
+  path: Mutex<Option<PathBuf>>,
+}
+
+impl Default for TransferQueue {
+  fn default() -> Self {
+    Self {
+      inner: Mutex::new(QueueInner {
+        jobs: Vec::new(),
+        concurrency: DEFAULT_CONCURRENCY,
+        active: 0,
+        dirty: false,
+        next_id: 0,
+        pausing: BTreeSet::new(),
+      }),
+      wake: Condvar::new(),
+      path: Mutex::new(None),
+    }
+  }
+}
+
+impl TransferQueue {
+  fn lock(&self) -> Result<MutexGuard<'_, QueueInner>, AppError> {
+    self.inner.lock().map_err(map_err)
+  }
+
+  /// Restores the queue saved under `dir`. Jobs that were running when the
+  /// app stopped go back to the queue.
+  pub fn load(&self, dir: PathBuf) -> Result<(), AppError> {
+    let path = dir.join(QUEUE_FILE);
+    if let Ok(raw) = fs::read_to_string(&path) {
+      let file: QueueFile = serde_json::from_str(&raw).map_err(map_err)?;
+      let mut inner = self.lock()?;
+      inner.concurrency = file.concurrency.clamp(1, MAX_CONCURRENCY);
+      inner.jobs = file
+        .jobs
+        .into_iter()
+        .map(|mut job| {
+          if job.status == JobStatus::Active {
+            job.status = JobStatus::Queued;
+          }
+          job
+        })
+        .collect();
+    }
+    *self.path.lock().map_err(map_err)? = Some(path);
+    Ok(())
+  }
+
+  fn save(&self, inner: &mut QueueInner) -> Result<(), AppError> {
+    let path = match self.path.lock().map_err(map_err)?.clone() {
+      Some(path) => path,
+      None => return Ok(()),
+    };
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent).map_err(map_err)?;
+    }
+    let file = QueueFile {
+      version: QUEUE_VERSION,
+      concurrency: inner.concurrency,
+      jobs: inner.jobs.clone(),
+    };
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec(&file).map_err(map_err)?).map_err(map_err)?;
+    fs::rename(&tmp, &path).map_err(map_err)?;
+    inner.dirty = false;
+    Ok(())
+  }
+
+  fn changed(&self, mut inner: MutexGuard<'_, QueueInner>) {
+    inner.dirty = true;
+    drop(inner);
+    self.wake.notify_all();
+  }
+
+  pub fn snapshot(&self) -> Result<QueueSnapshot, AppError> {
+    let inner = self.lock()?;
+    Ok(QueueSnapshot {
+      concurrency: inner.concurrency,
+      jobs: inner.jobs.clone(),
+    })
+  }
+
+  pub fn enqueue(
+    &self,
+    session_id: &str,
+    session: &Session,
+    requests: Vec<EnqueueRequest>,
+  ) -> Result<Vec<String>, AppError> {
+    let mut inner = self.lock()?;
+    let mut ids = Vec::with_capacity(requests.len());
+    let batch = if requests.len() > 1 {
+      inner.next_id += 1;
+      Some(format!("batch-{}-{}", now_millis(), inner.next_id))
+    } else {
+      None
+    };
+    for request in requests {
+      let id = match request.id {
+        Some(id) => id,
+        None => {
+          inner.next_id += 1;
+          format!("job-{}-{}", now_millis(), inner.next_id)
+        }
+      };
+      let size = request.size.or_else(|| match request.direction {
+        TransferDirection::Upload => fs::metadata(&request.local_path).ok().map(|meta| meta.len()),
+        TransferDirection::Download => None,
+      });
+      inner.jobs.push(TransferJob {
+        id: id.clone(),
+        direction: request.direction,
+        local_path: request.local_path,
+        remote_path: session.resolve_path(&request.remote_path),
+        session_key: session.key(),
+        session_id: Some(session_id.to_string()),
+        status: JobStatus::Queued,
+        error: None,
+        created: now_millis(),
+        conflict_policy: request.conflict_policy,
+        batch: batch.clone(),
+        size,
+      });
+      ids.push(id);
+    }
+    self.changed(inner);
+    Ok(ids)
+  }
+
+  /// Moves jobs between two waiting states. `None` applies to every job.
+  fn set_waiting_status(
+    &self,
+    ids: Option<Vec<String>>,
+    from: &[JobStatus],
+    to: JobStatus,
+  ) -> Result<Vec<String>, AppError> {
+    let mut inner = self.lock()?;
+    let mut changed = Vec::new();
+    for job in inner.jobs.iter_mut() {
+      let selected = ids.as_ref().map(|ids| ids.contains(&job.id)).unwrap_or(true);
+      if selected && from.contains(&job.status) {
+        job.status = to;
+        changed.push(job.id.clone());
+      }
+    }
+    self.changed(inner);
+    Ok(changed)
+  }
+
+  /// Holds queued jobs and stops running ones, which are marked paused once
+  /// their transfer returns.
+  pub fn pause(&self, ids: Option<Vec<String>>, cancels: &Cancellations) -> Result<Vec<String>, AppError> {
+    let mut changed = self.set_waiting_status(ids.clone(), &[JobStatus::Queued], JobStatus::Paused)?;
+    let mut inner = self.lock()?;
+    let running: Vec<String> = inner
+      .jobs
+      .iter()
+      .filter(|job| job.status == JobStatus::Active)
+      .filter(|job| ids.as_ref().map(|ids| ids.contains(&job.id)).unwrap_or(true))
+      .map(|job| job.id.clone())
+      .collect();
+    for id in running {
+      if cancels.pause(&id) {
+        inner.pausing.insert(id.clone());
+        changed.push(id);
+      }
+    }
+    Ok(changed)
+  }
+
+  /// Also requeues failed jobs, which doubles as "retry".
+  pub fn resume(&self, ids: Option<Vec<String>>) -> Result<Vec<String>, AppError> {
+    self.set_waiting_status(ids, &[JobStatus::Paused, JobStatus::Failed], JobStatus::Queued)
+  }
+
+  /// Running jobs are asked to stop and marked cancelled once their transfer
+  /// returns.
+  pub fn cancel(&self, ids: Option<Vec<String>>, cancels: &Cancellations) -> Result<Vec<String>, AppError> {
+    let mut changed =
+      self.set_waiting_status(ids.clone(), &[JobStatus::Queued, JobStatus::Paused], JobStatus::Cancelled)?;
+    let mut inner = self.lock()?;
+    let inner = &mut *inner;
+    for job in inner.jobs.iter().filter(|job| job.status == JobStatus::Active) {
+      let selected = ids.as_ref().map(|ids| ids.contains(&job.id)).unwrap_or(true);
+      if selected && cancels.cancel(&job.id) {
+        inner.pausing.remove(&job.id);
+        changed.push(job.id.clone());
+      }
+    }
+    Ok(changed)
+  }
+
+  pub fn reprioritize(&self, id: &str, position: usize) -> Result<(), AppError> {
+    let mut inner = self.lock()?;
+    let index = inner
+      .jobs
+      .iter()
+      .position(|job| job.id == id)
+      .ok_or_else(|| format!("Unknown transfer: {}", id))?;
+    let job = inner.jobs.remove(index);
+    let position = position.min(inner.jobs.len());
+    inner.jobs.insert(position, job);
+    self.changed(inner);
+    Ok(())
+  }
+
+  pub fn set_concurrency(&self, concurrency: usize) -> Result<(), AppError> {
+    let mut inner = self.lock()?;
+    inner.concurrency = concurrency.clamp(1, MAX_CONCURRENCY);
+    self.changed(inner);
+    Ok(())
+  }
+
+  pub fn clear_finished(&self) -> Result<(), AppError> {
+    let mut inner = self.lock()?;
+    inner.jobs.retain(|job| {
+      !matches!(
+        job.status,
+        JobStatus::Done | JobStatus::Cancelled
+      )
+    });
+    self.changed(inner);
+    Ok(())
+  }
+
+  /// Totals for the jobs of `batch` that were not cancelled. Running jobs add
+  /// the bytes they have moved so far.
+  fn batch_progress(inner: &QueueInner, batch: &str, progress: &ProgressTracker) -> BatchProgress {
+    let jobs: Vec<&TransferJob> = inner
+      .jobs
+      .iter()
+      .filter(|job| job.batch.as_deref() == Some(batch) && job.status != JobStatus::Cancelled)
+      .collect();
+    let files_done = jobs
+      .iter()
+      .filter(|job| matches!(job.status, JobStatus::Done | JobStatus::Failed))
+      .count();
+    let bytes_done = jobs
+      .iter()
+      .map(|job| match job.status {
+        JobStatus::Done => job.size.unwrap_or(0),
+        JobStatus::Active => progress.transferred(&job.id).unwrap_or(0),
+        _ => 0,
+      })
+      .sum();
+    let bytes_total = jobs.iter().map(|job| job.size).sum::<Option<u64>>();
+    progress.batch(batch, files_done, jobs.len(), bytes_done, bytes_total)
+  }
+
+  /// Picks the first queued job whose server currently has a session.
+  fn next_runnable(inner: &mut QueueInner, state: &AppState) -> Option<(TransferJob, Arc<Session>)> {
+    for job in inner.jobs.iter_mut() {
+      if job.status != JobStatus::Queued {
+        continue;
+      }
+      let session = job
+        .session_id
+        .as_deref()
+        .and_then(|id| state.sessions.get(id).ok())
+        .or_else(|| {
+          state
+            .sessions
+            .find_by_key(&job.session_key)
+            .map(|(id, session)| {
+              job.session_id = Some(id);
+              session
+            })
+        });
+      if let Some(session) = session {
+        job.status = JobStatus::Active;
+        job.error = None;
+        return Some((job.clone(), session));
+      }
+    }
+    None
+  }
+
+  fn finish(&self, app: &AppHandle, id: &str, result: Result<(), AppError>) {
+    // A transfer that failed before it started never unregistered itself.
+    app.state::<AppState>().cancels.finish(id);
+    if let Ok(mut inner) = self.inner.lock() {
+      inner.active = inner.active.saturating_sub(1);
+      let paused = inner.pausing.remove(id);
+      let (status, error) = match result {
+        Ok(_) => (JobStatus::Done, None),
+        Err(err) if err.kind == ErrorKind::Cancelled && paused => (JobStatus::Paused, None),
+        Err(err) if err.kind == ErrorKind::Cancelled => (JobStatus::Cancelled, None),
+        Err(err) => (JobStatus::Failed, Some(err.message)),
+      };
+      let mut batch = None;
+      if let Some(job) = inner.jobs.iter_mut().find(|job| job.id == id) {
+        job.status = status;
+        job.error = error.clone();
+        batch = job.batch.clone();
+      }
+      if let Some(batch) = batch {
+        let progress = Self::batch_progress(&inner, &batch, &app.state::<AppState>().progress);
+        let _ = app.emit_all("batch-progress", progress);
+      }
+      self.changed(inner);
+      let _ = app.emit_all(
+        "queue-job",
+        JobUpdate {
+          id: id.to_string(),
+          status,
+          error,
+        },
+      );
+    }
+  }
+
+  /// Runs the dispatcher loop on a background thread for the app's lifetime.
+  pub fn start(app: AppHandle) {
+    std::thread::spawn(move || {
+      let state = app.state::<AppState>();
+      let queue = &state.queue;
+      let mut last_save = Instant::now();
+      let mut last_batch_emit = Instant::now();
+      loop {
+        let mut inner = match queue.inner.lock() {
+          Ok(inner) => inner,
+          Err(_) => return,
+        };
+        while inner.active < inner.concurrency {
+          let (job, session) = match Self::next_runnable(&mut inner, &state) {
+            Some(next) => next,
+            None => break,
+          };
+          inner.active += 1;
+          inner.dirty = true;
+          // Registered while the queue is still locked, so a pause or cancel
+          // that sees the job active always reaches the transfer.
+          state.cancels.begin(&job.id);
+          let _ = app.emit_all(
+            "queue-job",
+            JobUpdate {
+              id: job.id.clone(),
+              status: JobStatus::Active,
+              error: None,
+            },
+          );
+          let app = app.clone();
+          std::thread::spawn(move || {
+            let result = match app.get_window("main") {
+              Some(window) => match job.direction {
+                TransferDirection::Download => {
+                  run_download(&session, &window, &job.id, &job.remote_path, &job.local_path, job.conflict_policy, None)
+                }
+                TransferDirection::Upload => {
+                  run_upload(&session, &window, &job.id, &job.local_path, &job.remote_path, job.conflict_policy, None)
+                }
+              },
+              None => Err("Main window is not available".into()),
+            };
+            app.state::<AppState>().queue.finish(&app, &job.id, result);
+          });
+        }
+        if last_batch_emit.elapsed() >= SAVE_INTERVAL {
+          let running: BTreeSet<&str> = inner
+            .jobs
+            .iter()
+            .filter(|job| job.status == JobStatus::Active)
+            .filter_map(|job| job.batch.as_deref())
+            .collect();
+          for batch in running {
+            let _ = app.emit_all("batch-progress", Self::batch_progress(&inner, batch, &state.progress));
+          }
+          last_batch_emit = Instant::now();
+        }
+        if inner.dirty && last_save.elapsed() >= SAVE_INTERVAL {
+          let _ = queue.save(&mut inner);
+          last_save = Instant::now();
+        }
+        // Wake periodically so jobs waiting for a reconnect get picked up.
+        let _ = queue.wake.wait_timeout(inner, SAVE_INTERVAL);
+      }
+    });
+  }
+}
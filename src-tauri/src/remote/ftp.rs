@@ -0,0 +1,685 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+use suppaftp::native_tls::TlsStream;
+use suppaftp::types::{FileType, Mode};
+use suppaftp::{FtpError, NativeTlsFtpStream, Status};
+use tauri::Window;
+
+use super::{ReadSink, RemoteFs, RemoteStat};
+use crate::error::{AppError, ErrorKind};
+use crate::tls::{self, CertificateValidation, FtpSecurity, SessionTls};
+use crate::list_parser::{parse_list_entries, parse_mlsd_entry};
+use crate::sites::{PortRange, TransferMode};
+use crate::verify::VerifyMethod;
+use crate::{connect_tcp, log_event, map_err, normalize_cwd, ConnectConfig, FtpEntry};
+
+/// Servers hash the whole file before replying, which for large files takes
+/// far longer than the usual control timeout.
+const CHECKSUM_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// `HASH` algorithm names (draft-bryan-ftpext-hash) in order of preference.
+const HASH_ALGORITHMS: [(&str, VerifyMethod); 4] = [
+  ("SHA-256", VerifyMethod::Sha256),
+  ("SHA-1", VerifyMethod::Sha1),
+  ("MD5", VerifyMethod::Md5),
+  ("CRC32", VerifyMethod::Crc32),
+];
+
+/// The older single-algorithm commands, in order of preference.
+const X_COMMANDS: [(&str, VerifyMethod); 4] = [
+  ("XSHA256", VerifyMethod::Sha256),
+  ("XSHA1", VerifyMethod::Sha1),
+  ("XMD5", VerifyMethod::Md5),
+  ("XCRC", VerifyMethod::Crc32),
+];
+
+pub struct FtpFs {
+  stream: NativeTlsFtpStream,
+  /// FEAT reply captured at login, keyed by upper-case feature name.
+  features: HashMap<String, Option<String>>,
+  secure: bool,
+  /// The data connection mode last given to suppaftp, which has no getter.
+  mode: Mode,
+  /// Set in active mode, where data connections are handled here rather
+  /// than by suppaftp, which cannot pick the port or announced address.
+  active: Option<ActiveMode>,
+}
+
+struct ActiveMode {
+  ports: Option<PortRange>,
+  external_ip: Option<IpAddr>,
+  /// Wraps data connections when the control connection uses TLS.
+  tls: Option<(SessionTls, String)>,
+  timeout: Duration,
+}
+
+/// An active-mode data connection accepted from the server.
+enum DataConnection {
+  Plain(TcpStream),
+  Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl Read for DataConnection {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    match self {
+      DataConnection::Plain(stream) => stream.read(buf),
+      DataConnection::Tls(stream) => stream.read(buf),
+    }
+  }
+}
+
+impl Write for DataConnection {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    match self {
+      DataConnection::Plain(stream) => stream.write(buf),
+      DataConnection::Tls(stream) => stream.write(buf),
+    }
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    match self {
+      DataConnection::Plain(stream) => stream.flush(),
+      DataConnection::Tls(stream) => stream.flush(),
+    }
+  }
+}
+
+impl DataConnection {
+  /// Ends an upload; the server only treats a TLS upload as complete after
+  /// the close notification.
+  fn close(self) -> std::io::Result<()> {
+    match self {
+      DataConnection::Plain(stream) => stream.shutdown(std::net::Shutdown::Write),
+      DataConnection::Tls(mut stream) => stream.shutdown(),
+    }
+  }
+}
+
+/// Counts the bytes pulled from an upload source so a failed STOR can tell
+/// whether it is still safe to retry with the same reader.
+struct CountingReader<'a> {
+  inner: &'a mut dyn Read,
+  read: u64,
+}
+
+impl Read for CountingReader<'_> {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    let read = self.inner.read(buf)?;
+    self.read += read as u64;
+    Ok(read)
+  }
+}
+
+/// Finds the checksum in a `213 SHA-256 0-49 <hash> <file>` or
+/// `250 <hash>` reply. The reply code and the byte range are skipped, and the
+/// file name follows the hash.
+fn parse_checksum(reply: &str, method: VerifyMethod) -> Option<String> {
+  let mut tokens = reply.split_whitespace().peekable();
+  if tokens
+    .peek()
+    .is_some_and(|token| token.len() >= 3 && token[..3].bytes().all(|byte| byte.is_ascii_digit()))
+  {
+    tokens.next();
+  }
+  let expected = match method {
+    VerifyMethod::Sha256 => 64..=64,
+    VerifyMethod::Sha1 => 40..=40,
+    VerifyMethod::Md5 => 32..=32,
+    VerifyMethod::Crc32 => 1..=8,
+    VerifyMethod::Size => return None,
+  };
+  tokens
+    .filter(|token| !token.contains('-'))
+    .find(|token| expected.contains(&token.len()) && token.bytes().all(|byte| byte.is_ascii_hexdigit()))
+    .map(str::to_ascii_lowercase)
+}
+
+fn should_retry_with_epsv(err: &FtpError) -> bool {
+  match err {
+    FtpError::ConnectionError(io_err) => {
+      io_err.kind() == std::io::ErrorKind::TimedOut || io_err.raw_os_error() == Some(10060)
+    }
+    FtpError::UnexpectedResponse(response) => response.status.code() == 425,
+    _ => false,
+  }
+}
+
+/// Extracts the port from a `227 Entering Passive Mode (h1,h2,h3,h4,p1,p2)`
+/// reply.
+fn parse_pasv_port(reply: &str) -> Option<u16> {
+  let start = reply.find(|ch: char| ch.is_ascii_digit())?;
+  let numbers: Vec<u16> = reply[start..]
+    .split(|ch: char| !ch.is_ascii_digit())
+    .filter(|part| !part.is_empty())
+    .take(6)
+    .map(|part| part.parse().ok())
+    .collect::<Option<_>>()?;
+  if numbers.len() != 6 || numbers[4] > 255 || numbers[5] > 255 {
+    return None;
+  }
+  Some(numbers[4] << 8 | numbers[5])
+}
+
+/// `PORT h1,h2,h3,h4,p1,p2` for IPv4, `EPRT |2|addr|port|` for IPv6.
+fn port_command(address: SocketAddr) -> String {
+  match address.ip() {
+    IpAddr::V4(ip) => {
+      let octets = ip.octets();
+      format!(
+        "PORT {},{},{},{},{},{}",
+        octets[0],
+        octets[1],
+        octets[2],
+        octets[3],
+        address.port() >> 8,
+        address.port() & 0xff
+      )
+    }
+    IpAddr::V6(ip) => format!("EPRT |2|{}|{}|", ip, address.port()),
+  }
+}
+
+/// Listens on the first free port of `ports`, or any port without a range.
+fn bind_listener(ip: IpAddr, ports: Option<PortRange>) -> Result<TcpListener, AppError> {
+  let range = match ports {
+    Some(range) => range,
+    None => return TcpListener::bind((ip, 0)).map_err(map_err),
+  };
+  (range.start..=range.end)
+    .find_map(|port| TcpListener::bind((ip, port)).ok())
+    .ok_or_else(|| format!("No free port in {}-{} for active mode", range.start, range.end).into())
+}
+
+fn accept_within(listener: &TcpListener, timeout: Duration) -> Result<TcpStream, AppError> {
+  listener.set_nonblocking(true).map_err(map_err)?;
+  let deadline = Instant::now() + timeout;
+  loop {
+    match listener.accept() {
+      Ok((stream, _)) => {
+        stream.set_nonblocking(false).map_err(map_err)?;
+        stream.set_read_timeout(Some(timeout)).map_err(map_err)?;
+        stream.set_write_timeout(Some(timeout)).map_err(map_err)?;
+        return Ok(stream);
+      }
+      Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+        if Instant::now() >= deadline {
+          return Err(AppError::new(
+          ErrorKind::NotConnected,
+          "Server did not open the active mode data connection",
+        ));
+        }
+        std::thread::sleep(Duration::from_millis(20));
+      }
+      Err(err) => return Err(map_err(err)),
+    }
+  }
+}
+
+fn open_stream(
+  window: &Window,
+  config: &ConnectConfig,
+  timeout: Duration,
+) -> Result<(NativeTlsFtpStream, Option<SessionTls>), AppError> {
+  let host = config.host.trim();
+  if config.security == FtpSecurity::None {
+    let stream = connect_tcp(host, config.port, timeout)?;
+    return Ok((NativeTlsFtpStream::connect_with_stream(stream).map_err(map_err)?, None));
+  }
+
+  let session_tls = SessionTls::new(&config.certificate)?;
+  let connector = session_tls.ftp_connector();
+  let result = if config.security == FtpSecurity::Implicit {
+    NativeTlsFtpStream::connect_secure_implicit((host, config.port), connector, host)
+  } else {
+    let stream = connect_tcp(host, config.port, timeout)?;
+    NativeTlsFtpStream::connect_with_stream(stream).and_then(|ftp| ftp.into_secure(connector, host))
+  };
+  let ftp = match result {
+    Ok(ftp) => ftp,
+    Err(FtpError::SecureError(reason))
+      if matches!(config.certificate, CertificateValidation::AcceptOnce { .. }) =>
+    {
+      let prompt = tls::certificate_prompt(host, config.port, config.security, timeout, reason)?;
+      log_event(
+        window,
+        "error",
+        format!("Untrusted certificate for {} ({})", host, prompt.fingerprint),
+      );
+      let _ = window.emit("tls-certificate-prompt", prompt);
+      return Err(AppError::new(ErrorKind::TlsError, "Certificate not trusted"));
+    }
+    Err(err) => return Err(map_err(err)),
+  };
+  if config.security == FtpSecurity::Implicit {
+    let stream = ftp.get_ref();
+    stream.set_read_timeout(Some(timeout)).map_err(map_err)?;
+    stream.set_write_timeout(Some(timeout)).map_err(map_err)?;
+  }
+  Ok((ftp, Some(session_tls)))
+}
+
+impl FtpFs {
+  pub fn connect(window: &Window, config: &ConnectConfig, timeout: Duration) -> Result<Self, AppError> {
+    let (mut stream, tls) = open_stream(window, config, timeout)?;
+    stream
+      .login(&config.username, &config.password)
+      .map_err(map_err)?;
+    let mut active = None;
+    let mut mode = Mode::Passive;
+    match config.transfer_mode {
+      TransferMode::Passive => stream.set_passive_nat_workaround(true),
+      TransferMode::ExtendedPassive => {
+        mode = Mode::ExtendedPassive;
+        stream.set_mode(mode);
+      }
+      TransferMode::Active => {
+        let external_ip = match config.external_ip.as_deref().map(str::trim) {
+          Some(ip) if !ip.is_empty() => Some(
+            ip.parse::<IpAddr>()
+              .map_err(|_| format!("Invalid external IP address: {}", ip))?,
+          ),
+          _ => None,
+        };
+        active = Some(ActiveMode {
+          ports: config.active_ports,
+          external_ip,
+          tls: tls.map(|tls| (tls, config.host.trim().to_string())),
+          timeout,
+        });
+      }
+    }
+    stream.transfer_type(FileType::Binary).map_err(map_err)?;
+    // Servers without FEAT simply get the LIST-based fallbacks.
+    let features = stream
+      .feat()
+      .map(|features| {
+        features
+          .into_iter()
+          .map(|(name, value)| (name.to_ascii_uppercase(), value))
+          .collect()
+      })
+      .unwrap_or_default();
+    Ok(Self {
+      stream,
+      features,
+      secure: config.security != FtpSecurity::None,
+      mode,
+      active,
+    })
+  }
+
+  /// Announces a listening port, sends `command` and accepts the server's
+  /// data connection for it. A non-zero `offset` sends REST right before the
+  /// command, which must follow it immediately.
+  fn open_active(&mut self, command: String, offset: u64) -> Result<DataConnection, AppError> {
+    let active = self
+      .active
+      .as_ref()
+      .ok_or_else(|| "Not in active mode".to_string())?;
+    let local_ip = self.stream.get_ref().local_addr().map_err(map_err)?.ip();
+    let listener = bind_listener(local_ip, active.ports)?;
+    let port = listener.local_addr().map_err(map_err)?.port();
+    let announced = SocketAddr::new(active.external_ip.unwrap_or(local_ip), port);
+    let tls = active.tls.clone();
+    let timeout = active.timeout;
+    self
+      .stream
+      .custom_command(port_command(announced), &[Status::CommandOk])
+      .map_err(map_err)?;
+    if offset > 0 {
+      self.stream.resume_transfer(offset as usize).map_err(map_err)?;
+    }
+    self
+      .stream
+      .custom_command(command, &[Status::AboutToSend, Status::AlreadyOpen])
+      .map_err(map_err)?;
+    let stream = accept_within(&listener, timeout)?;
+    match tls {
+      Some((tls, host)) => Ok(DataConnection::Tls(Box::new(tls.connect(&host, stream)?))),
+      None => Ok(DataConnection::Plain(stream)),
+    }
+  }
+
+  /// Reads a listing over an active-mode data connection.
+  fn list_active(&mut self, command: &str, path: Option<&str>) -> Result<Vec<String>, AppError> {
+    let command = match path {
+      Some(path) => format!("{} {}", command, path),
+      None => command.to_string(),
+    };
+    let data = self.open_active(command, 0)?;
+    let lines = BufReader::new(data)
+      .lines()
+      .collect::<std::io::Result<Vec<String>>>()
+      .map_err(map_err);
+    let done = self.wait_transfer_complete();
+    let lines = lines?;
+    done?;
+    Ok(lines)
+  }
+
+  fn supports(&self, feature: &str) -> bool {
+    self.features.contains_key(feature)
+  }
+
+  /// Writes a command without waiting for its reply, for the FXP sequence
+  /// where both servers must be told to transfer before either answers.
+  fn send(&mut self, command: &str) -> Result<(), AppError> {
+    let mut stream = self.stream.get_ref();
+    stream
+      .write_all(format!("{}\r\n", command).as_bytes())
+      .map_err(map_err)
+  }
+
+  /// Waits for the end-of-transfer reply, which takes as long as the transfer
+  /// itself, so the control timeout is lifted meanwhile.
+  fn wait_transfer_complete(&mut self) -> Result<(), AppError> {
+    let previous = self.stream.get_ref().read_timeout().map_err(map_err)?;
+    self.stream.get_ref().set_read_timeout(None).map_err(map_err)?;
+    // The data connection is already closed; this only reads the 226/250.
+    let result = self.stream.close_data_connection(std::io::empty());
+    let _ = self.stream.get_ref().set_read_timeout(previous);
+    result.map_err(map_err)
+  }
+
+  /// Reads the preliminary reply to a command written with `send`. The
+  /// socket is read a byte at a time so the final reply stays unread for
+  /// suppaftp.
+  fn read_started(&mut self) -> Result<(), AppError> {
+    let mut reader = BufReader::with_capacity(1, self.stream.get_ref());
+    let (code, text) = tls::read_reply(&mut reader)?;
+    match code {
+      125 | 150 => Ok(()),
+      _ => Err(AppError::reply(code, format!("Invalid response: [{}] {}", code, text))),
+    }
+  }
+
+  /// Runs a checksum command with the longer checksum timeout.
+  fn checksum_reply(&mut self, command: String, method: VerifyMethod) -> Result<Option<String>, AppError> {
+    let previous = self.stream.get_ref().read_timeout().map_err(map_err)?;
+    self
+      .stream
+      .get_ref()
+      .set_read_timeout(Some(CHECKSUM_TIMEOUT))
+      .map_err(map_err)?;
+    let result = self.stream.custom_command(
+      command,
+      &[Status::File, Status::RequestedFileActionOk, Status::CommandOk],
+    );
+    let _ = self.stream.get_ref().set_read_timeout(previous);
+    let reply = result.map_err(map_err)?;
+    Ok(parse_checksum(&String::from_utf8_lossy(&reply.body), method))
+  }
+
+  /// STOR from the start, or APPE to continue at `offset`.
+  fn store(&mut self, path: &str, offset: u64, reader: &mut CountingReader<'_>) -> Result<u64, FtpError> {
+    let mut data = if offset > 0 {
+      self.stream.append_with_stream(path)?
+    } else {
+      self.stream.put_with_stream(path)?
+    };
+    match std::io::copy(reader, &mut data) {
+      Ok(written) => {
+        self.stream.finalize_put_stream(data)?;
+        Ok(written)
+      }
+      Err(err) => {
+        let _ = self.stream.abort(data);
+        Err(FtpError::ConnectionError(err))
+      }
+    }
+  }
+
+  /// Sends ABOR for a transfer stopped midway, by a cancel or a local error,
+  /// so the control connection stays in step for the next command.
+  /// A failed ABOR leaves the connection to the reconnect logic.
+  fn abort(&mut self, data: impl Read + 'static, err: std::io::Error) -> AppError {
+    let _ = self.stream.abort(data);
+    map_err(err)
+  }
+}
+
+/// How an FXP attempt ended, short of failing mid-transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fxp {
+  Copied,
+  /// Refused during setup; both connections are still usable.
+  Refused,
+  /// STOR or RETR was refused before any data moved. Replies may still be
+  /// pending on either connection, so both must be replaced.
+  Abandoned,
+}
+
+/// Server-to-server (FXP) copy: the target listens with PASV and the source
+/// connects to it with PORT, so the data never passes through this machine.
+/// Errors once both servers started leave the connections in an unknown
+/// state. TLS connections are not used, since FXP over TLS needs CPSV/SSCN.
+pub fn fxp_copy(source: &mut FtpFs, target: &mut FtpFs, from: &str, to: &str) -> Result<Fxp, AppError> {
+  if source.secure || target.secure {
+    return Ok(Fxp::Refused);
+  }
+  let port = match target.stream.custom_command("PASV", &[Status::PassiveMode]) {
+    Ok(reply) => match parse_pasv_port(&String::from_utf8_lossy(&reply.body)) {
+      Some(port) => port,
+      None => return Ok(Fxp::Refused),
+    },
+    Err(_) => return Ok(Fxp::Refused),
+  };
+  // The PASV address may be private; the source needs the one we reached.
+  let octets = match target.stream.get_ref().peer_addr().map_err(map_err)?.ip() {
+    IpAddr::V4(ip) => ip.octets(),
+    IpAddr::V6(_) => return Ok(Fxp::Refused),
+  };
+  let command = format!(
+    "PORT {},{},{},{},{},{}",
+    octets[0],
+    octets[1],
+    octets[2],
+    octets[3],
+    port >> 8,
+    port & 0xff
+  );
+  if source.stream.custom_command(command, &[Status::CommandOk]).is_err() {
+    return Ok(Fxp::Refused);
+  }
+  let started = target
+    .send(&format!("STOR {}", to))
+    .and_then(|_| source.send(&format!("RETR {}", from)))
+    .and_then(|_| source.read_started())
+    .and_then(|_| target.read_started());
+  if started.is_err() {
+    return Ok(Fxp::Abandoned);
+  }
+  source.wait_transfer_complete()?;
+  target.wait_transfer_complete()?;
+  Ok(Fxp::Copied)
+}
+
+
+impl RemoteFs for FtpFs {
+  fn pwd(&mut self) -> Result<String, AppError> {
+    Ok(normalize_cwd(self.stream.pwd().map_err(map_err)?))
+  }
+
+  fn cwd(&mut self, path: &str) -> Result<(), AppError> {
+    self.stream.cwd(path).map_err(map_err)
+  }
+
+  fn list(&mut self, path: Option<&str>) -> Result<Vec<FtpEntry>, AppError> {
+    // MLST in FEAT advertises both MLST and MLSD (RFC 3659).
+    let active = self.active.is_some();
+    if self.supports("MLST") {
+      let listing = if active {
+        self.list_active("MLSD", path)?
+      } else {
+        self.stream.mlsd(path).map_err(map_err)?
+      };
+      return Ok(listing.iter().filter_map(|line| parse_mlsd_entry(line)).collect());
+    }
+    let listing = if active {
+      self.list_active("LIST", path)?
+    } else {
+      self.stream.list(path).map_err(map_err)?
+    };
+    Ok(parse_list_entries(listing))
+  }
+
+  fn stat(&mut self, path: &str) -> Result<RemoteStat, AppError> {
+    if self.supports("MLST") {
+      if let Some(entry) = self
+        .stream
+        .mlst(Some(path))
+        .ok()
+        .and_then(|line| parse_mlsd_entry(&line))
+      {
+        return Ok(RemoteStat {
+          size: entry.size,
+          is_dir: entry.is_dir,
+          modified: entry.modified,
+        });
+      }
+    }
+    if let Ok(size) = self.stream.size(path) {
+      let modified = self
+        .stream
+        .mdtm(path)
+        .ok()
+        .map(|time| time.and_utc().timestamp_millis());
+      return Ok(RemoteStat {
+        size: Some(size as u64),
+        is_dir: false,
+        modified,
+      });
+    }
+    let current = self.stream.pwd().map_err(map_err)?;
+    self.stream.cwd(path).map_err(map_err)?;
+    self.stream.cwd(current).map_err(map_err)?;
+    Ok(RemoteStat {
+      size: None,
+      is_dir: true,
+      modified: None,
+    })
+  }
+
+  fn get(&mut self, path: &str, offset: u64, sink: &mut ReadSink<'_>) -> Result<u64, AppError> {
+    if self.active.is_some() {
+      let mut data = self.open_active(format!("RETR {}", path), offset)?;
+      let read = match sink(&mut data) {
+        Ok(read) => read,
+        Err(err) => return Err(self.abort(data, err)),
+      };
+      drop(data);
+      self.wait_transfer_complete()?;
+      return Ok(read);
+    }
+    if offset > 0 {
+      self.stream.resume_transfer(offset as usize).map_err(map_err)?;
+    }
+    let mut data = self.stream.retr_as_stream(path).map_err(map_err)?;
+    match sink(&mut data) {
+      Ok(read) => {
+        self.stream.finalize_retr_stream(data).map_err(map_err)?;
+        Ok(read)
+      }
+      Err(err) => Err(self.abort(data, err)),
+    }
+  }
+
+  fn put(&mut self, path: &str, offset: u64, reader: &mut dyn Read) -> Result<u64, AppError> {
+    if self.active.is_some() {
+      let command = if offset > 0 { "APPE" } else { "STOR" };
+      let mut data = self.open_active(format!("{} {}", command, path), 0)?;
+      let written = match std::io::copy(reader, &mut data) {
+        Ok(written) => written,
+        Err(err) => return Err(self.abort(data, err)),
+      };
+      let closed = data.close();
+      let done = self.wait_transfer_complete();
+      closed.map_err(map_err)?;
+      done?;
+      return Ok(written);
+    }
+    let mut counted = CountingReader { inner: reader, read: 0 };
+    match self.store(path, offset, &mut counted) {
+      Err(err) if counted.read == 0 && self.mode != Mode::ExtendedPassive && should_retry_with_epsv(&err) => {
+        self.stream.set_mode(Mode::ExtendedPassive);
+        let result = self.store(path, offset, &mut counted);
+        self.stream.set_mode(self.mode);
+        result.map_err(map_err)
+      }
+      result => result.map_err(map_err),
+    }
+  }
+
+  fn mkdir(&mut self, path: &str) -> Result<(), AppError> {
+    self.stream.mkdir(path).map_err(map_err)
+  }
+
+  fn rm(&mut self, path: &str) -> Result<(), AppError> {
+    self.stream.rm(path).map_err(map_err)
+  }
+
+  fn rmdir(&mut self, path: &str) -> Result<(), AppError> {
+    self.stream.rmdir(path).map_err(map_err)
+  }
+
+  fn rename(&mut self, from: &str, to: &str) -> Result<(), AppError> {
+    self.stream.rename(from, to).map_err(map_err)
+  }
+
+  fn quit(&mut self) {
+    let _ = self.stream.quit();
+  }
+
+  fn noop(&mut self) -> Result<(), AppError> {
+    self.stream.noop().map_err(map_err)
+  }
+
+  fn as_ftp(&mut self) -> Option<&mut FtpFs> {
+    Some(self)
+  }
+
+  /// Prefers `HASH` with the strongest advertised algorithm, then the `X*`
+  /// commands. FEAT lists HASH algorithms like `SHA-1;SHA-256*;MD5`.
+  fn checksum(&mut self, path: &str) -> Result<Option<(VerifyMethod, String)>, AppError> {
+    let hash_algorithm = self
+      .features
+      .get("HASH")
+      .cloned()
+      .flatten()
+      .and_then(|advertised| {
+        let advertised: Vec<String> = advertised
+          .split(';')
+          .map(|name| name.trim().trim_end_matches('*').to_ascii_uppercase())
+          .collect();
+        HASH_ALGORITHMS
+          .iter()
+          .find(|(name, _)| advertised.iter().any(|advertised| advertised == name))
+          .copied()
+      });
+    if let Some((name, method)) = hash_algorithm {
+      if self
+        .stream
+        .custom_command(format!("OPTS HASH {}", name), &[Status::CommandOk])
+        .is_ok()
+      {
+        if let Some(sum) = self.checksum_reply(format!("HASH {}", path), method)? {
+          return Ok(Some((method, sum)));
+        }
+      }
+    }
+    let command = X_COMMANDS
+      .iter()
+      .find(|(command, _)| self.supports(command))
+      .copied();
+    match command {
+      Some((command, method)) => Ok(
+        self
+          .checksum_reply(format!("{} {}", command, path), method)?
+          .map(|sum| (method, sum)),
+      ),
+      None => Ok(None),
+    }
+  }
+}
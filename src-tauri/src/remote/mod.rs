@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::time::Duration;
+use tauri::Window;
+
+use crate::error::AppError;
+use crate::verify::VerifyMethod;
+use crate::{ConnectConfig, FtpEntry};
+
+mod ftp;
+mod sftp;
+
+pub use ftp::{fxp_copy, FtpFs, Fxp};
+pub use sftp::SftpFs;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Protocol {
+  #[default]
+  Ftp,
+  Sftp,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RemoteStat {
+  pub size: Option<u64>,
+  pub is_dir: bool,
+  /// Milliseconds since the Unix epoch, when the server reports it.
+  pub modified: Option<i64>,
+}
+
+/// Receives the data stream of a download and returns the bytes it consumed.
+pub type ReadSink<'a> = dyn FnMut(&mut dyn Read) -> std::io::Result<u64> + 'a;
+
+/// Protocol-agnostic view of a logged-in remote file system. Relative paths
+/// resolve against the current directory of the connection.
+pub trait RemoteFs: Send {
+  fn pwd(&mut self) -> Result<String, AppError>;
+  fn cwd(&mut self, path: &str) -> Result<(), AppError>;
+  fn list(&mut self, path: Option<&str>) -> Result<Vec<FtpEntry>, AppError>;
+  fn stat(&mut self, path: &str) -> Result<RemoteStat, AppError>;
+  /// Streams the file starting `offset` bytes in, so partial downloads can
+  /// be resumed.
+  fn get(&mut self, path: &str, offset: u64, sink: &mut ReadSink<'_>) -> Result<u64, AppError>;
+  /// Writes the stream to the file. A non-zero `offset` continues a partial
+  /// upload by appending at that position.
+  fn put(&mut self, path: &str, offset: u64, reader: &mut dyn Read) -> Result<u64, AppError>;
+  fn mkdir(&mut self, path: &str) -> Result<(), AppError>;
+  fn rm(&mut self, path: &str) -> Result<(), AppError>;
+  fn rmdir(&mut self, path: &str) -> Result<(), AppError>;
+  fn rename(&mut self, from: &str, to: &str) -> Result<(), AppError>;
+  fn quit(&mut self);
+  /// A cheap round trip that fails once the connection is gone.
+  fn noop(&mut self) -> Result<(), AppError>;
+
+  /// Hex checksum of a remote file computed by the server, for servers that
+  /// offer one.
+  fn checksum(&mut self, _path: &str) -> Result<Option<(VerifyMethod, String)>, AppError> {
+    Ok(None)
+  }
+
+  /// The FTP connection behind this remote, for FTP-only operations like FXP.
+  fn as_ftp(&mut self) -> Option<&mut FtpFs> {
+    None
+  }
+}
+
+pub fn open_remote(
+  window: &Window,
+  config: &ConnectConfig,
+  timeout: Duration,
+) -> Result<Box<dyn RemoteFs>, AppError> {
+  match config.protocol {
+    Protocol::Ftp => Ok(Box::new(FtpFs::connect(window, config, timeout)?)),
+    Protocol::Sftp => Ok(Box::new(SftpFs::connect(window, config, timeout)?)),
+  }
+}
@@ -0,0 +1,234 @@
+use ssh2::{CheckResult, KnownHostFileKind, OpenFlags, OpenType, Session, Sftp};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::Window;
+
+use super::{ReadSink, RemoteFs, RemoteStat};
+use crate::error::{AppError, ErrorKind};
+use crate::tls::{self, CertificatePrompt, CertificateValidation};
+use crate::{connect_tcp, join_remote, log_event, map_err, normalize_cwd, ConnectConfig, FtpEntry};
+
+/// The user's OpenSSH `known_hosts`, when there is a home directory.
+fn known_hosts_path() -> Option<PathBuf> {
+  std::env::var_os("HOME")
+    .or_else(|| std::env::var_os("USERPROFILE"))
+    .map(|home| PathBuf::from(home).join(".ssh").join("known_hosts"))
+}
+
+/// Checks the server's host key before any credentials are sent. The
+/// profile's certificate setting applies as it does for FTPS: a pinned
+/// fingerprint alone decides, otherwise the key must be in `known_hosts`,
+/// and `AcceptOnce` asks the user about unknown keys with the same
+/// `tls-certificate-prompt` event.
+fn check_host_key(window: &Window, session: &Session, config: &ConnectConfig) -> Result<(), AppError> {
+  let host = config.host.trim();
+  let (key, _) = session
+    .host_key()
+    .ok_or_else(|| AppError::new(ErrorKind::NotConnected, "Server did not present a host key"))?;
+  let fingerprint = tls::sha256_fingerprint(key);
+  let untrusted = |reason: String| {
+    AppError::new(ErrorKind::Other, format!("Host key for {} is not trusted: {}", host, reason))
+  };
+  if let CertificateValidation::Pinned { fingerprint: expected } = &config.certificate {
+    return if tls::fingerprints_match(&fingerprint, expected) {
+      Ok(())
+    } else {
+      Err(untrusted(format!("fingerprint mismatch (server presented {})", fingerprint)))
+    };
+  }
+
+  let mut known_hosts = session.known_hosts().map_err(map_err)?;
+  if let Some(path) = known_hosts_path().filter(|path| path.is_file()) {
+    if let Err(err) = known_hosts.read_file(&path, KnownHostFileKind::OpenSSH) {
+      log_event(window, "error", format!("Unable to read {}: {}", path.display(), err));
+    }
+  }
+  let reason = match known_hosts.check_port(host, config.port, key) {
+    CheckResult::Match => return Ok(()),
+    // A changed key is refused outright, as OpenSSH does.
+    CheckResult::Mismatch => {
+      return Err(untrusted(format!(
+        "it differs from the key in known_hosts (server presented {})",
+        fingerprint
+      )))
+    }
+    CheckResult::NotFound | CheckResult::Failure => "host key is not in known_hosts".to_string(),
+  };
+  match &config.certificate {
+    CertificateValidation::AcceptOnce {
+      fingerprint: Some(accepted),
+    } if tls::fingerprints_match(&fingerprint, accepted) => Ok(()),
+    CertificateValidation::AcceptOnce { .. } => {
+      log_event(window, "error", format!("Unknown host key for {} ({})", host, fingerprint));
+      let _ = window.emit(
+        "tls-certificate-prompt",
+        CertificatePrompt {
+          host: host.to_string(),
+          port: config.port,
+          fingerprint,
+          reason,
+        },
+      );
+      Err(untrusted("not accepted".to_string()))
+    }
+    _ => Err(untrusted(reason)),
+  }
+}
+
+/// SFTP has no server-side working directory, so the connection tracks one and
+/// resolves relative paths against it.
+pub struct SftpFs {
+  session: Session,
+  sftp: Sftp,
+  cwd: String,
+}
+
+impl SftpFs {
+  pub fn connect(window: &Window, config: &ConnectConfig, timeout: Duration) -> Result<Self, AppError> {
+    let stream = connect_tcp(config.host.trim(), config.port, timeout)?;
+    let mut session = Session::new().map_err(map_err)?;
+    session.set_tcp_stream(stream);
+    session.set_timeout(timeout.as_millis() as u32);
+    session.handshake().map_err(map_err)?;
+    check_host_key(window, &session, config)?;
+    session
+      .userauth_password(&config.username, &config.password)
+      .map_err(map_err)?;
+    if !session.authenticated() {
+      return Err(AppError::new(ErrorKind::AuthFailed, "Authentication failed"));
+    }
+    let sftp = session.sftp().map_err(map_err)?;
+    let cwd = sftp
+      .realpath(Path::new("."))
+      .map(|path| normalize_cwd(path.to_string_lossy().to_string()))
+      .unwrap_or_else(|_| "/".to_string());
+    Ok(Self { session, sftp, cwd })
+  }
+
+  fn resolve(&self, path: &str) -> String {
+    if path.starts_with('/') {
+      path.to_string()
+    } else {
+      join_remote(&self.cwd, path)
+    }
+  }
+}
+
+impl RemoteFs for SftpFs {
+  fn pwd(&mut self) -> Result<String, AppError> {
+    Ok(self.cwd.clone())
+  }
+
+  fn cwd(&mut self, path: &str) -> Result<(), AppError> {
+    let target = self.resolve(path);
+    let resolved = self.sftp.realpath(Path::new(&target)).map_err(map_err)?;
+    let stat = self.sftp.stat(&resolved).map_err(map_err)?;
+    if !stat.is_dir() {
+      return Err(format!("Not a directory: {}", target).into());
+    }
+    self.cwd = normalize_cwd(resolved.to_string_lossy().to_string());
+    Ok(())
+  }
+
+  fn list(&mut self, path: Option<&str>) -> Result<Vec<FtpEntry>, AppError> {
+    let target = path.map(|value| self.resolve(value)).unwrap_or_else(|| self.cwd.clone());
+    let listing = self.sftp.readdir(Path::new(&target)).map_err(map_err)?;
+    Ok(
+      listing
+        .into_iter()
+        .filter_map(|(path, stat)| {
+          let name = path.file_name()?.to_string_lossy().to_string();
+          if name == "." || name == ".." {
+            return None;
+          }
+          let mut entry = FtpEntry {
+            name,
+            size: if stat.is_dir() { None } else { stat.size },
+            modified: stat.mtime.map(|seconds| seconds as i64 * 1000),
+            is_dir: stat.is_dir(),
+            is_symlink: stat.file_type().is_symlink(),
+            permissions: stat.perm.map(|perm| format!("{:o}", perm & 0o7777)),
+            owner: stat.uid.map(|uid| uid.to_string()),
+            group: stat.gid.map(|gid| gid.to_string()),
+            ..Default::default()
+          };
+          // READDIR describes the link itself, which is what `is_dir`
+          // reports, so recursive walks and deletes never follow links.
+          if entry.is_symlink {
+            entry.link_target = self
+              .sftp
+              .readlink(&path)
+              .ok()
+              .map(|target| target.to_string_lossy().to_string());
+          }
+          Some(entry)
+        })
+        .collect(),
+    )
+  }
+
+  fn stat(&mut self, path: &str) -> Result<RemoteStat, AppError> {
+    let stat = self.sftp.stat(Path::new(&self.resolve(path))).map_err(map_err)?;
+    Ok(RemoteStat {
+      size: if stat.is_dir() { None } else { stat.size },
+      is_dir: stat.is_dir(),
+      modified: stat.mtime.map(|secs| secs as i64 * 1000),
+    })
+  }
+
+  fn get(&mut self, path: &str, offset: u64, sink: &mut ReadSink<'_>) -> Result<u64, AppError> {
+    let mut file = self.sftp.open(Path::new(&self.resolve(path))).map_err(map_err)?;
+    if offset > 0 {
+      file.seek(SeekFrom::Start(offset)).map_err(map_err)?;
+    }
+    sink(&mut file).map_err(map_err)
+  }
+
+  fn put(&mut self, path: &str, offset: u64, reader: &mut dyn Read) -> Result<u64, AppError> {
+    let target = self.resolve(path);
+    let mut file = if offset > 0 {
+      let mut file = self
+        .sftp
+        .open_mode(Path::new(&target), OpenFlags::WRITE, 0o644, OpenType::File)
+        .map_err(map_err)?;
+      file.seek(SeekFrom::Start(offset)).map_err(map_err)?;
+      file
+    } else {
+      self.sftp.create(Path::new(&target)).map_err(map_err)?
+    };
+    std::io::copy(reader, &mut file).map_err(map_err)
+  }
+
+  fn mkdir(&mut self, path: &str) -> Result<(), AppError> {
+    self
+      .sftp
+      .mkdir(Path::new(&self.resolve(path)), 0o755)
+      .map_err(map_err)
+  }
+
+  fn rm(&mut self, path: &str) -> Result<(), AppError> {
+    self.sftp.unlink(Path::new(&self.resolve(path))).map_err(map_err)
+  }
+
+  fn rmdir(&mut self, path: &str) -> Result<(), AppError> {
+    self.sftp.rmdir(Path::new(&self.resolve(path))).map_err(map_err)
+  }
+
+  fn rename(&mut self, from: &str, to: &str) -> Result<(), AppError> {
+    let from = self.resolve(from);
+    let to = self.resolve(to);
+    self
+      .sftp
+      .rename(Path::new(&from), Path::new(&to), None)
+      .map_err(map_err)
+  }
+
+  fn quit(&mut self) {
+    let _ = self.session.disconnect(None, "Goodbye", None);
+  }
+
+  fn noop(&mut self) -> Result<(), AppError> {
+    self.sftp.realpath(Path::new(".")).map(|_| ()).map_err(map_err)
+  }
+}
@@ -0,0 +1,261 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use tauri::Window;
+
+use crate::error::{AppError, ErrorKind};
+use crate::remote::{open_remote, RemoteFs};
+use crate::{join_remote, log_event, map_err, ConnectConfig};
+
+/// Idle transfer connections kept per session for reuse.
+const MAX_IDLE_WORKERS: usize = 2;
+/// Connections idle this long get a NOOP, well inside the usual server and
+/// NAT idle timeouts.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// One logged-in server. Commands on different sessions never wait on each
+/// other; commands on the same session serialize on `remote`. Transfers run
+/// on separate worker connections so browsing stays responsive.
+pub struct Session {
+  pub config: ConnectConfig,
+  pub remote: Mutex<Box<dyn RemoteFs>>,
+  pub cwd: Mutex<String>,
+  workers: Mutex<Vec<Box<dyn RemoteFs>>>,
+  /// Uploads that failed part-way, as (local, remote) path pairs.
+  partial_uploads: Mutex<HashSet<(String, String)>>,
+  last_used: Mutex<Instant>,
+  /// Set when a keepalive found the browsing connection dead.
+  stale: AtomicBool,
+  closed: AtomicBool,
+}
+
+impl Session {
+  fn touch(&self) {
+    if let Ok(mut last_used) = self.last_used.lock() {
+      *last_used = Instant::now();
+    }
+  }
+
+  /// Runs `command` on the browsing connection. If it fails and the server no
+  /// longer answers, logs in again and returns to the last directory; an
+  /// idempotent command is then run once more, anything else reports the
+  /// original error.
+  pub fn with_remote<T>(
+    &self,
+    window: &Window,
+    idempotent: bool,
+    mut command: impl FnMut(&mut dyn RemoteFs) -> Result<T, AppError>,
+  ) -> Result<T, AppError> {
+    let mut remote = self.remote.lock().map_err(map_err)?;
+    self.touch();
+    if self.stale.swap(false, Ordering::Relaxed) {
+      self.reconnect(window, &mut remote)?;
+    }
+    let err = match command(remote.as_mut()) {
+      Ok(value) => return Ok(value),
+      Err(err) => err,
+    };
+    // Any reply means the connection is fine and the error is the server's.
+    if remote.noop().is_ok() {
+      return Err(err);
+    }
+    self.reconnect(window, &mut remote)?;
+    if !idempotent {
+      return Err(err);
+    }
+    command(remote.as_mut())
+  }
+
+  fn reconnect(&self, window: &Window, remote: &mut Box<dyn RemoteFs>) -> Result<(), AppError> {
+    log_event(window, "info", "Connection lost, reconnecting");
+    let mut fresh = open_remote(window, &self.config, self.config.timeout())?;
+    let cwd = self.cwd.lock().map_err(map_err)?.clone();
+    if !cwd.is_empty() {
+      if let Err(err) = fresh.cwd(&cwd) {
+        log_event(window, "error", format!("Unable to reopen {}: {}", cwd, err));
+      }
+    }
+    // The old connection is dropped without QUIT, which would only wait for
+    // a reply that never comes. Idle workers most likely died with it.
+    *remote = fresh;
+    if let Ok(mut workers) = self.workers.lock() {
+      workers.clear();
+    }
+    log_event(window, "success", "Reconnected");
+    Ok(())
+  }
+
+  /// Sends NOOP on connections idle for the keepalive interval and drops
+  /// idle workers that no longer answer.
+  fn keep_alive(&self) {
+    let idle = self
+      .last_used
+      .lock()
+      .map(|last_used| last_used.elapsed())
+      .unwrap_or_default();
+    if idle < KEEPALIVE_INTERVAL {
+      return;
+    }
+    // A connection that is busy is alive by definition.
+    if let Ok(mut remote) = self.remote.try_lock() {
+      if remote.noop().is_err() {
+        self.stale.store(true, Ordering::Relaxed);
+      }
+    }
+    let idle_workers = match self.workers.lock() {
+      Ok(mut workers) => std::mem::take(&mut *workers),
+      Err(_) => return,
+    };
+    let alive: Vec<Box<dyn RemoteFs>> = idle_workers
+      .into_iter()
+      .filter_map(|mut worker| worker.noop().ok().map(|_| worker))
+      .collect();
+    for worker in alive {
+      self.checkin_worker(worker);
+    }
+    self.touch();
+  }
+
+  /// Takes an idle worker connection or logs in a new one with the session's
+  /// credentials.
+  pub fn checkout_worker(&self, window: &Window) -> Result<Box<dyn RemoteFs>, AppError> {
+    if let Some(worker) = self.workers.lock().map_err(map_err)?.pop() {
+      return Ok(worker);
+    }
+    open_remote(window, &self.config, self.config.timeout())
+  }
+
+  /// Returns a worker after a successful transfer. Workers that failed should
+  /// be dropped instead, since their control connection may be out of sync.
+  pub fn checkin_worker(&self, mut worker: Box<dyn RemoteFs>) {
+    if let Ok(mut workers) = self.workers.lock() {
+      if workers.len() < MAX_IDLE_WORKERS {
+        workers.push(worker);
+        return;
+      }
+    }
+    worker.quit();
+  }
+
+  /// Stable identity of the server and account, independent of the session id.
+  pub fn key(&self) -> String {
+    format!(
+      "{:?}://{}@{}:{}",
+      self.config.protocol,
+      self.config.username,
+      self.config.host.trim(),
+      self.config.port
+    )
+    .to_lowercase()
+  }
+
+  /// Workers start in the login directory, so relative paths are anchored to
+  /// the browsing connection's current directory.
+  pub fn resolve_path(&self, path: &str) -> String {
+    if path.starts_with('/') {
+      return path.to_string();
+    }
+    match self.cwd.lock() {
+      Ok(cwd) if !cwd.is_empty() => join_remote(&cwd, path),
+      _ => path.to_string(),
+    }
+  }
+
+  pub fn record_partial_upload(&self, local_path: &str, remote_path: &str) {
+    if let Ok(mut partial) = self.partial_uploads.lock() {
+      partial.insert((local_path.to_string(), remote_path.to_string()));
+    }
+  }
+
+  /// Whether the remote file holds the prefix of an earlier failed upload
+  /// from `local_path`, in which case it is safe to append to it.
+  pub fn take_partial_upload(&self, local_path: &str, remote_path: &str) -> bool {
+    self
+      .partial_uploads
+      .lock()
+      .map(|mut partial| partial.remove(&(local_path.to_string(), remote_path.to_string())))
+      .unwrap_or(false)
+  }
+
+  pub fn close(&self) {
+    self.closed.store(true, Ordering::Relaxed);
+    if let Ok(mut remote) = self.remote.lock() {
+      remote.quit();
+    }
+    if let Ok(mut workers) = self.workers.lock() {
+      for mut worker in workers.drain(..) {
+        worker.quit();
+      }
+    }
+  }
+}
+
+/// Keeps a session's connections alive until it is closed or dropped.
+fn spawn_keepalive(session: Weak<Session>) {
+  std::thread::spawn(move || loop {
+    std::thread::sleep(KEEPALIVE_INTERVAL);
+    match session.upgrade() {
+      Some(session) if !session.closed.load(Ordering::Relaxed) => session.keep_alive(),
+      _ => return,
+    }
+  });
+}
+
+#[derive(Default)]
+pub struct SessionRegistry {
+  sessions: Mutex<HashMap<String, Arc<Session>>>,
+  next_id: AtomicU64,
+}
+
+impl SessionRegistry {
+  pub fn insert(
+    &self,
+    config: ConnectConfig,
+    remote: Box<dyn RemoteFs>,
+    cwd: String,
+  ) -> Result<String, AppError> {
+    let id = format!("session-{}", self.next_id.fetch_add(1, Ordering::Relaxed) + 1);
+    let session = Arc::new(Session {
+      config,
+      remote: Mutex::new(remote),
+      cwd: Mutex::new(cwd),
+      workers: Mutex::new(Vec::new()),
+      partial_uploads: Mutex::new(HashSet::new()),
+      last_used: Mutex::new(Instant::now()),
+      stale: AtomicBool::new(false),
+      closed: AtomicBool::new(false),
+    });
+    spawn_keepalive(Arc::downgrade(&session));
+    self
+      .sessions
+      .lock()
+      .map_err(map_err)?
+      .insert(id.clone(), session);
+    Ok(id)
+  }
+
+  pub fn get(&self, id: &str) -> Result<Arc<Session>, AppError> {
+    self
+      .sessions
+      .lock()
+      .map_err(map_err)?
+      .get(id)
+      .cloned()
+      .ok_or_else(|| AppError::new(ErrorKind::NotConnected, "Not connected"))
+  }
+
+  pub fn find_by_key(&self, key: &str) -> Option<(String, Arc<Session>)> {
+    self
+      .sessions
+      .lock()
+      .ok()?
+      .iter()
+      .find(|(_, session)| session.key() == key)
+      .map(|(id, session)| (id.clone(), session.clone()))
+  }
+
+  pub fn remove(&self, id: &str) -> Result<Option<Arc<Session>>, AppError> {
+    Ok(self.sessions.lock().map_err(map_err)?.remove(id))
+  }
+}
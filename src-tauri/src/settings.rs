@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::error::AppError;
+use crate::map_err;
+use crate::throttle::ThrottleConfig;
+
+const SETTINGS_FILE: &str = "settings.json";
+const SETTINGS_VERSION: u32 = 1;
+
+/// What to do when a transfer's target already exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+  #[default]
+  Overwrite,
+  Skip,
+  /// Keep both by giving the new file a ` (1)` style suffix.
+  Rename,
+  OverwriteIfNewer,
+  OverwriteIfSizeDiffers,
+  /// Ask the frontend through a `transfer-conflict` event.
+  Ask,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TransferDefaults {
+  pub concurrency: usize,
+  pub conflict_policy: ConflictPolicy,
+  /// Connect and read timeout for servers without their own.
+  pub timeout_secs: u64,
+  pub buffer_size: usize,
+  pub default_local_dir: Option<String>,
+  /// Compare checksums, or at least sizes, after every transfer.
+  pub verify: bool,
+  /// Leave the partial file of a cancelled transfer so it can be resumed.
+  pub keep_partial_on_cancel: bool,
+}
+
+impl Default for TransferDefaults {
+  fn default() -> Self {
+    Self {
+      concurrency: 2,
+      conflict_policy: ConflictPolicy::default(),
+      timeout_secs: 10,
+      buffer_size: 128 * 1024,
+      default_local_dir: None,
+      verify: false,
+      keep_partial_on_cancel: true,
+    }
+  }
+}
+
+impl TransferDefaults {
+  /// Clamps values a hand-edited file could get wrong.
+  fn sanitize(&mut self) {
+    self.concurrency = self.concurrency.clamp(1, 16);
+    self.timeout_secs = self.timeout_secs.clamp(1, 600);
+    self.buffer_size = self.buffer_size.clamp(4 * 1024, 8 * 1024 * 1024);
+  }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Preferences {
+  pub open_on_startup: bool,
+  pub close_to_tray: bool,
+  pub minimize_to_tray: bool,
+  pub transfers: TransferDefaults,
+  pub throttle: ThrottleConfig,
+}
+
+/// Fields the frontend wants to change; missing ones keep their value.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreferencesUpdate {
+  pub open_on_startup: Option<bool>,
+  pub close_to_tray: Option<bool>,
+  pub minimize_to_tray: Option<bool>,
+  pub transfers: Option<TransferDefaults>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SettingsFile {
+  version: u32,
+  #[serde(default)]
+  preferences: Preferences,
+}
+
+#[derive(Default)]
+pub struct Settings {
+  prefs: Mutex<Preferences>,
+  path: Option<PathBuf>,
+}
+
+impl Settings {
+  /// Reads `settings.json` from `dir`. Runs before the window opens, so a
+  /// bad file falls back to defaults instead of failing startup; what went
+  /// wrong is returned for the log.
+  pub fn load(dir: Option<PathBuf>) -> (Self, Option<String>) {
+    let path = dir.map(|dir| dir.join(SETTINGS_FILE));
+    let mut prefs = Preferences::default();
+    let mut problem = None;
+    if let Some(raw) = path.as_ref().and_then(|path| fs::read_to_string(path).ok()) {
+      match serde_json::from_str::<SettingsFile>(&raw) {
+        Ok(file) if file.version <= SETTINGS_VERSION => prefs = file.preferences,
+        Ok(file) => problem = Some(format!("Unsupported settings version {}", file.version)),
+        Err(err) => problem = Some(format!("Failed to read settings: {}", err)),
+      }
+    }
+    prefs.transfers.sanitize();
+    let settings = Self {
+      prefs: Mutex::new(prefs),
+      path,
+    };
+    (settings, problem)
+  }
+
+  pub fn get(&self) -> Result<Preferences, AppError> {
+    Ok(self.prefs.lock().map_err(map_err)?.clone())
+  }
+
+  /// Applies `change` and writes the result to disk. The preferences in
+  /// memory only change once the write succeeded.
+  pub fn update(&self, change: impl FnOnce(&mut Preferences)) -> Result<Preferences, AppError> {
+    let mut current = self.prefs.lock().map_err(map_err)?;
+    let mut prefs = current.clone();
+    change(&mut prefs);
+    prefs.transfers.sanitize();
+    if let Some(path) = &self.path {
+      if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(map_err)?;
+      }
+      let file = SettingsFile {
+        version: SETTINGS_VERSION,
+        preferences: prefs.clone(),
+      };
+      let tmp = path.with_extension("json.tmp");
+      fs::write(&tmp, serde_json::to_vec_pretty(&file).map_err(map_err)?).map_err(map_err)?;
+      fs::rename(&tmp, path).map_err(map_err)?;
+    }
+    *current = prefs.clone();
+    Ok(prefs)
+  }
+}
@@ -0,0 +1,236 @@
+use base64::engine::general_purpose::STANDARD as BASE64_ENGINE;
+use base64::Engine;
+use roxmltree::Node;
+
+use super::{ImportedSite, ParsedImport, SiteOptions, SiteProfile, TransferMode};
+use crate::error::AppError;
+use crate::map_err;
+use crate::remote::Protocol;
+use crate::tls::FtpSecurity;
+
+/// Reads the `Servers` tree of a FileZilla `sitemanager.xml`. Nested
+/// `Folder` elements become the profile's folder path.
+pub fn parse(raw: &str) -> Result<ParsedImport, AppError> {
+  let document = roxmltree::Document::parse(raw).map_err(map_err)?;
+  let mut parsed = ParsedImport::default();
+  for servers in document
+    .root_element()
+    .children()
+    .filter(|node| node.has_tag_name("Servers"))
+  {
+    walk(servers, &mut Vec::new(), &mut parsed);
+  }
+  Ok(parsed)
+}
+
+fn walk(node: Node, folders: &mut Vec<String>, parsed: &mut ParsedImport) {
+  for child in node.children().filter(|child| child.is_element()) {
+    match child.tag_name().name() {
+      "Folder" => {
+        folders.push(own_text(child));
+        walk(child, folders, parsed);
+        folders.pop();
+      }
+      "Server" => read_server(child, folders, parsed),
+      _ => {}
+    }
+  }
+}
+
+/// Text directly inside an element, ignoring its child elements. Folder names
+/// and old-style server names are stored this way.
+fn own_text(node: Node) -> String {
+  node
+    .children()
+    .filter(|child| child.is_text())
+    .filter_map(|child| child.text())
+    .collect::<String>()
+    .trim()
+    .to_string()
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+  node
+    .children()
+    .find(|child| child.has_tag_name(name))
+    .and_then(|child| child.text())
+    .map(str::trim)
+    .filter(|text| !text.is_empty())
+}
+
+fn read_server(node: Node, folders: &[String], parsed: &mut ParsedImport) {
+  let host = match child_text(node, "Host") {
+    Some(host) => host,
+    None => return,
+  };
+  let name = child_text(node, "Name")
+    .map(str::to_string)
+    .unwrap_or_else(|| own_text(node));
+  let name = if name.is_empty() { host.to_string() } else { name };
+
+  let (protocol, security, default_port) = match child_text(node, "Protocol").unwrap_or("0") {
+    "0" | "6" => (Protocol::Ftp, FtpSecurity::None, 21),
+    "1" => (Protocol::Sftp, FtpSecurity::None, 22),
+    "3" => (Protocol::Ftp, FtpSecurity::Implicit, 990),
+    "4" => (Protocol::Ftp, FtpSecurity::Explicit, 21),
+    other => {
+      parsed
+        .warnings
+        .push(format!("{}: unsupported FileZilla protocol {}", name, other));
+      return;
+    }
+  };
+
+  let password = match node.children().find(|child| child.has_tag_name("Pass")) {
+    Some(pass) => match pass.attribute("encoding") {
+      Some("base64") => pass
+        .text()
+        .and_then(|text| BASE64_ENGINE.decode(text.trim()).ok())
+        .and_then(|bytes| String::from_utf8(bytes).ok()),
+      None | Some("plain") => pass.text().map(str::to_string),
+      Some(_) => {
+        parsed.warnings.push(format!(
+          "{}: password is protected by a FileZilla master password",
+          name
+        ));
+        None
+      }
+    },
+    None => None,
+  };
+
+  // Logon type 0 is FileZilla's anonymous login, saved without a user.
+  let username = match child_text(node, "Logontype") {
+    Some("0") => "anonymous".to_string(),
+    _ => child_text(node, "User").unwrap_or_default().to_string(),
+  };
+
+  let profile = SiteProfile {
+    name,
+    folder: if folders.is_empty() {
+      None
+    } else {
+      Some(folders.join("/"))
+    },
+    host: host.to_string(),
+    port: child_text(node, "Port")
+      .and_then(|port| port.parse().ok())
+      .unwrap_or(default_port),
+    username,
+    protocol,
+    security,
+    remote_dir: child_text(node, "RemoteDir").and_then(parse_remote_dir),
+    local_dir: child_text(node, "LocalDir").map(str::to_string),
+    transfer_mode: match child_text(node, "PasvMode") {
+      Some("MODE_ACTIVE") => TransferMode::Active,
+      _ => TransferMode::Passive,
+    },
+    options: SiteOptions {
+      notes: child_text(node, "Comments").unwrap_or_default().to_string(),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  parsed.sites.push(ImportedSite {
+    profile,
+    password: password.filter(|password| !password.is_empty()),
+  });
+}
+
+fn next_token<'a>(rest: &mut &'a str) -> Option<&'a str> {
+  if rest.is_empty() {
+    return None;
+  }
+  let (token, tail) = rest.split_once(' ').unwrap_or((rest, ""));
+  *rest = tail;
+  Some(token)
+}
+
+fn take_chars<'a>(rest: &mut &'a str, count: usize) -> Option<&'a str> {
+  let end = match rest.char_indices().nth(count) {
+    Some((index, _)) => index,
+    None if rest.chars().count() == count => rest.len(),
+    None => return None,
+  };
+  let (taken, tail) = rest.split_at(end);
+  *rest = tail.strip_prefix(' ').unwrap_or(tail);
+  Some(taken)
+}
+
+/// FileZilla stores server paths as `type prefix-length [prefix] (length
+/// segment)*`, e.g. `1 0 4 home 5 files` for `/home/files` on a Unix server.
+fn parse_remote_dir(raw: &str) -> Option<String> {
+  let mut rest = raw;
+  next_token(&mut rest)?;
+  let prefix_len: usize = next_token(&mut rest)?.parse().ok()?;
+  if prefix_len > 0 {
+    take_chars(&mut rest, prefix_len)?;
+  }
+  let mut segments = Vec::new();
+  while let Some(len) = next_token(&mut rest) {
+    let len: usize = len.parse().ok()?;
+    segments.push(take_chars(&mut rest, len)?);
+  }
+  Some(format!("/{}", segments.join("/")))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use std::path::Path;
+
+  /// `tests/fixtures/sites/sitemanager.xml` is a FileZilla 3 export.
+  fn fixture() -> ParsedImport {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sites/sitemanager.xml");
+    parse(&fs::read_to_string(path).unwrap()).unwrap()
+  }
+
+  #[test]
+  fn imports_sitemanager_fixture() {
+    let parsed = fixture();
+    assert_eq!(parsed.sites.len(), 4);
+
+    let acme = &parsed.sites[0];
+    assert_eq!(acme.profile.name, "Acme FTPS");
+    assert_eq!(acme.profile.folder.as_deref(), Some("Clients/Acme"));
+    assert_eq!(acme.profile.host, "ftp.acme.example");
+    assert_eq!(acme.profile.port, 21);
+    assert_eq!(acme.profile.security, FtpSecurity::Explicit);
+    assert_eq!(acme.profile.username, "deploy");
+    assert_eq!(acme.password.as_deref(), Some("s3cr3t!"));
+    assert_eq!(acme.profile.transfer_mode, TransferMode::Active);
+    assert_eq!(acme.profile.remote_dir.as_deref(), Some("/var/www/acme.com"));
+    assert_eq!(acme.profile.local_dir.as_deref(), Some("C:\\Sites\\acme"));
+    assert_eq!(acme.profile.options.notes, "Production");
+
+    let backups = &parsed.sites[1];
+    assert_eq!(backups.profile.folder, None);
+    assert_eq!(backups.profile.protocol, Protocol::Sftp);
+    assert_eq!(backups.profile.port, 2222);
+    assert_eq!(backups.password.as_deref(), Some("plain pass"));
+    assert_eq!(backups.profile.remote_dir.as_deref(), Some("/home/backup data"));
+
+    let mirror = &parsed.sites[2];
+    assert_eq!(mirror.profile.username, "anonymous");
+    assert_eq!(mirror.password, None);
+
+    let protected = &parsed.sites[3];
+    assert_eq!(protected.profile.security, FtpSecurity::Implicit);
+    assert_eq!(protected.password, None);
+    assert_eq!(
+      parsed.warnings,
+      ["Protected: password is protected by a FileZilla master password"]
+    );
+  }
+
+  #[test]
+  fn decodes_remote_dirs() {
+    assert_eq!(parse_remote_dir("1 0 4 home 5 files").as_deref(), Some("/home/files"));
+    assert_eq!(parse_remote_dir("1 0 6 my dir 1 x").as_deref(), Some("/my dir/x"));
+    assert_eq!(parse_remote_dir("1 0 5 größe").as_deref(), Some("/größe"));
+    assert_eq!(parse_remote_dir("1 0").as_deref(), Some("/"));
+    assert_eq!(parse_remote_dir("1 0 9 short"), None);
+    assert_eq!(parse_remote_dir("1 x"), None);
+  }
+}
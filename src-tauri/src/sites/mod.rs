@@ -0,0 +1,329 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::error::{AppError, ErrorKind};
+use crate::remote::Protocol;
+use crate::tls::{CertificateValidation, FtpSecurity};
+use crate::{map_err, now_millis, ConnectConfig};
+
+mod filezilla;
+mod winscp;
+
+const SITES_FILE: &str = "sites.json";
+const SITES_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferMode {
+  /// PASV, with the reply's address replaced by the server's when private.
+  #[default]
+  Passive,
+  /// EPSV, which also works over IPv6.
+  ExtendedPassive,
+  /// PORT or EPRT; the server connects back to us.
+  Active,
+}
+
+/// Local ports to listen on in active mode, both ends inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortRange {
+  pub start: u16,
+  pub end: u16,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SiteOptions {
+  /// Connect and read timeout for this server, overriding the default.
+  pub timeout_secs: Option<u64>,
+  pub notes: String,
+  /// Active mode only; any free port when unset.
+  pub active_ports: Option<PortRange>,
+  /// Address announced in active mode instead of the local one, for NAT.
+  pub external_ip: Option<String>,
+}
+
+/// A saved server. Passwords are never part of a profile; at most it holds a
+/// reference into the credential vault.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SiteProfile {
+  #[serde(default)]
+  pub id: String,
+  pub name: String,
+  /// Slash-separated group path, e.g. `Clients/Acme`.
+  #[serde(default)]
+  pub folder: Option<String>,
+  pub host: String,
+  pub port: u16,
+  #[serde(default)]
+  pub username: String,
+  #[serde(default)]
+  pub password_ref: Option<String>,
+  #[serde(default)]
+  pub protocol: Protocol,
+  #[serde(default)]
+  pub security: FtpSecurity,
+  #[serde(default)]
+  pub certificate: CertificateValidation,
+  #[serde(default)]
+  pub remote_dir: Option<String>,
+  #[serde(default)]
+  pub local_dir: Option<String>,
+  #[serde(default)]
+  pub transfer_mode: TransferMode,
+  #[serde(default)]
+  pub options: SiteOptions,
+}
+
+impl SiteProfile {
+  pub fn connect_config(&self) -> ConnectConfig {
+    ConnectConfig {
+      host: self.host.clone(),
+      port: self.port,
+      username: self.username.clone(),
+      password: String::new(),
+      password_ref: self.password_ref.clone(),
+      protocol: self.protocol,
+      security: self.security,
+      certificate: self.certificate.clone(),
+      timeout_secs: self.options.timeout_secs,
+      transfer_mode: self.transfer_mode,
+      active_ports: self.options.active_ports,
+      external_ip: self.options.external_ip.clone(),
+    }
+  }
+
+  fn validate(&self) -> Result<(), AppError> {
+    if self.name.trim().is_empty() {
+      return Err("Site name is required".into());
+    }
+    if self.host.trim().is_empty() {
+      return Err("Host is required".into());
+    }
+    if self.port == 0 {
+      return Err("Port is required".into());
+    }
+    if let Some(range) = self.options.active_ports {
+      if range.start == 0 || range.start > range.end {
+        return Err("Invalid active mode port range".into());
+      }
+    }
+    Ok(())
+  }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SitesFile {
+  version: u32,
+  sites: Vec<SiteProfile>,
+}
+
+#[derive(Default)]
+struct SitesInner {
+  sites: Vec<SiteProfile>,
+  next_id: u64,
+}
+
+#[derive(Default)]
+pub struct SiteStore {
+  inner: Mutex<SitesInner>,
+  path: Mutex<Option<PathBuf>>,
+}
+
+/// A site read from an import file, with its password if the file held one
+/// in recoverable form.
+pub struct ImportedSite {
+  pub profile: SiteProfile,
+  pub password: Option<String>,
+}
+
+#[derive(Default)]
+pub struct ParsedImport {
+  pub sites: Vec<ImportedSite>,
+  pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+  pub sites: Vec<SiteProfile>,
+  pub warnings: Vec<String>,
+}
+
+fn parse_sites_json(raw: &str) -> Result<Vec<SiteProfile>, AppError> {
+  let file: SitesFile = serde_json::from_str(raw).map_err(map_err)?;
+  if file.version > SITES_VERSION {
+    return Err(format!("Unsupported sites file version {}", file.version).into());
+  }
+  Ok(file.sites)
+}
+
+fn read_sites_file(path: &Path) -> Result<Vec<SiteProfile>, AppError> {
+  parse_sites_json(&fs::read_to_string(path).map_err(map_err)?)
+}
+
+/// Reads an exported sites file, a FileZilla `sitemanager.xml` or a
+/// `WinSCP.ini`, telling them apart by content.
+pub fn parse_import_file(path: &Path) -> Result<ParsedImport, AppError> {
+  let raw = fs::read_to_string(path).map_err(map_err)?;
+  let raw = raw.trim_start_matches('\u{feff}').trim_start();
+  if raw.starts_with('{') {
+    let sites = parse_sites_json(raw)?
+      .into_iter()
+      .map(|profile| ImportedSite {
+        profile: SiteProfile {
+          password_ref: None,
+          ..profile
+        },
+        password: None,
+      })
+      .collect();
+    Ok(ParsedImport {
+      sites,
+      warnings: Vec::new(),
+    })
+  } else if raw.starts_with('<') {
+    filezilla::parse(raw)
+  } else if raw.contains("[Sessions\\") {
+    winscp::parse(raw)
+  } else {
+    Err("Unrecognized sites file".into())
+  }
+}
+
+fn write_sites_file(path: &Path, sites: &[SiteProfile]) -> Result<(), AppError> {
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent).map_err(map_err)?;
+  }
+  let file = SitesFile {
+    version: SITES_VERSION,
+    sites: sites.to_vec(),
+  };
+  let tmp = path.with_extension("json.tmp");
+  fs::write(&tmp, serde_json::to_vec_pretty(&file).map_err(map_err)?).map_err(map_err)?;
+  fs::rename(&tmp, path).map_err(map_err)
+}
+
+impl SitesInner {
+  fn new_id(&mut self) -> String {
+    self.next_id += 1;
+    format!("site-{}-{}", now_millis(), self.next_id)
+  }
+}
+
+impl SiteStore {
+  /// Reads saved sites from `dir`. A missing file means no sites yet.
+  pub fn load(&self, dir: PathBuf) -> Result<(), AppError> {
+    let path = dir.join(SITES_FILE);
+    if path.exists() {
+      self.lock()?.sites = read_sites_file(&path)?;
+    }
+    *self.path.lock().map_err(map_err)? = Some(path);
+    Ok(())
+  }
+
+  fn lock(&self) -> Result<MutexGuard<'_, SitesInner>, AppError> {
+    self.inner.lock().map_err(map_err)
+  }
+
+  fn save(&self, inner: &SitesInner) -> Result<(), AppError> {
+    match self.path.lock().map_err(map_err)?.as_ref() {
+      Some(path) => write_sites_file(path, &inner.sites),
+      None => Ok(()),
+    }
+  }
+
+  pub fn list(&self) -> Result<Vec<SiteProfile>, AppError> {
+    Ok(self.lock()?.sites.clone())
+  }
+
+  pub fn get(&self, id: &str) -> Result<SiteProfile, AppError> {
+    self
+      .lock()?
+      .sites
+      .iter()
+      .find(|site| site.id == id)
+      .cloned()
+      .ok_or_else(|| AppError::new(ErrorKind::NotFound, format!("Unknown site: {}", id)))
+  }
+
+  pub fn add(&self, mut site: SiteProfile) -> Result<SiteProfile, AppError> {
+    site.validate()?;
+    let mut inner = self.lock()?;
+    site.id = inner.new_id();
+    inner.sites.push(site.clone());
+    self.save(&inner)?;
+    Ok(site)
+  }
+
+  pub fn update(&self, site: SiteProfile) -> Result<(), AppError> {
+    site.validate()?;
+    let mut inner = self.lock()?;
+    let existing = inner
+      .sites
+      .iter_mut()
+      .find(|existing| existing.id == site.id)
+      .ok_or_else(|| format!("Unknown site: {}", site.id))?;
+    *existing = site;
+    self.save(&inner)
+  }
+
+  pub fn delete(&self, id: &str) -> Result<(), AppError> {
+    let mut inner = self.lock()?;
+    let before = inner.sites.len();
+    inner.sites.retain(|site| site.id != id);
+    if inner.sites.len() == before {
+      return Err(AppError::new(ErrorKind::NotFound, format!("Unknown site: {}", id)));
+    }
+    self.save(&inner)
+  }
+
+  /// Adds imported sites under fresh ids. Sites that match an existing one by
+  /// name, host, port and user are left out.
+  pub fn merge(&self, sites: Vec<SiteProfile>) -> Result<Vec<SiteProfile>, AppError> {
+    let mut inner = self.lock()?;
+    let mut imported = Vec::new();
+    for mut site in sites {
+      if site.validate().is_err() {
+        continue;
+      }
+      let duplicate = inner.sites.iter().any(|existing| {
+        existing.name == site.name
+          && existing.host.eq_ignore_ascii_case(&site.host)
+          && existing.port == site.port
+          && existing.username == site.username
+      });
+      if duplicate {
+        continue;
+      }
+      site.id = inner.new_id();
+      inner.sites.push(site.clone());
+      imported.push(site);
+    }
+    if !imported.is_empty() {
+      self.save(&inner)?;
+    }
+    Ok(imported)
+  }
+
+  /// Writes the given sites, or all of them, in the same format `import` reads.
+  /// Vault references are machine-local and left out.
+  pub fn export(&self, path: &Path, ids: Option<Vec<String>>) -> Result<usize, AppError> {
+    let inner = self.lock()?;
+    let sites: Vec<SiteProfile> = inner
+      .sites
+      .iter()
+      .filter(|site| match &ids {
+        Some(ids) => ids.contains(&site.id),
+        None => true,
+      })
+      .map(|site| SiteProfile {
+        password_ref: None,
+        ..site.clone()
+      })
+      .collect();
+    write_sites_file(path, &sites)?;
+    Ok(sites.len())
+  }
+}
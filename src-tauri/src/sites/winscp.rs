@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+
+use super::{ImportedSite, ParsedImport, SiteOptions, SiteProfile, TransferMode};
+use crate::error::AppError;
+use crate::remote::Protocol;
+use crate::tls::FtpSecurity;
+
+const PWALG_SIMPLE_MAGIC: u8 = 0xA3;
+const PWALG_SIMPLE_FLAG: u8 = 0xFF;
+
+type Section = (String, HashMap<String, String>);
+
+fn parse_ini(raw: &str) -> Vec<Section> {
+  let mut sections: Vec<Section> = Vec::new();
+  for line in raw.lines() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with(';') {
+      continue;
+    }
+    if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+      sections.push((name.to_string(), HashMap::new()));
+    } else if let (Some((_, values)), Some((key, value))) = (sections.last_mut(), line.split_once('=')) {
+      values.insert(key.trim().to_string(), value.trim().to_string());
+    }
+  }
+  sections
+}
+
+/// WinSCP escapes session names and most values like URLs.
+fn decode(value: &str) -> String {
+  let bytes = value.as_bytes();
+  let mut out = Vec::with_capacity(bytes.len());
+  let mut index = 0;
+  while index < bytes.len() {
+    if bytes[index] == b'%' {
+      if let Some(byte) = value
+        .get(index + 1..index + 3)
+        .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+      {
+        out.push(byte);
+        index += 3;
+        continue;
+      }
+    }
+    out.push(bytes[index]);
+    index += 1;
+  }
+  String::from_utf8_lossy(&out).to_string()
+}
+
+/// Reverses WinSCP's password obfuscation, which is keyed on user name and
+/// host name. Not possible when a master password is set.
+fn decrypt_password(encrypted: &str, key: &str) -> Option<String> {
+  if !encrypted.is_ascii() || encrypted.len() % 2 == 1 {
+    return None;
+  }
+  let bytes = (0..encrypted.len() / 2)
+    .map(|index| {
+      u8::from_str_radix(&encrypted[index * 2..index * 2 + 2], 16)
+        .ok()
+        .map(|byte| !(byte ^ PWALG_SIMPLE_MAGIC))
+    })
+    .collect::<Option<Vec<u8>>>()?;
+  let mut bytes = bytes.into_iter();
+  let flag = bytes.next()?;
+  let length = if flag == PWALG_SIMPLE_FLAG {
+    bytes.next()?;
+    bytes.next()?
+  } else {
+    flag
+  } as usize;
+  let skip = bytes.next()? as usize;
+  let value: Vec<u8> = bytes.skip(skip).take(length).collect();
+  if value.len() != length {
+    return None;
+  }
+  let value = String::from_utf8(value).ok()?;
+  if flag == PWALG_SIMPLE_FLAG {
+    value.strip_prefix(key).map(str::to_string)
+  } else {
+    Some(value)
+  }
+}
+
+/// Reads the `Sessions\...` sections of a `WinSCP.ini`. Slashes in the
+/// session name separate folders.
+pub fn parse(raw: &str) -> Result<ParsedImport, AppError> {
+  let sections = parse_ini(raw);
+  let master_password = sections.iter().any(|(name, values)| {
+    name == "Configuration\\Security"
+      && values.get("UseMasterPassword").map(String::as_str) == Some("1")
+  });
+  let mut parsed = ParsedImport::default();
+  if master_password {
+    parsed
+      .warnings
+      .push("Passwords are protected by a WinSCP master password and were not imported".to_string());
+  }
+  for (name, values) in &sections {
+    let path = match name.strip_prefix("Sessions\\") {
+      Some(path) if path != "Default%20Settings" => path,
+      _ => continue,
+    };
+    read_session(path, values, master_password, &mut parsed);
+  }
+  Ok(parsed)
+}
+
+fn read_session(
+  path: &str,
+  values: &HashMap<String, String>,
+  master_password: bool,
+  parsed: &mut ParsedImport,
+) {
+  let value = |key: &str| values.get(key).map(|value| decode(value)).filter(|value| !value.is_empty());
+  let mut segments: Vec<String> = path.split('/').map(decode).collect();
+  let name = segments.pop().unwrap_or_default();
+  let host = match value("HostName") {
+    Some(host) => host,
+    None => return,
+  };
+
+  let (protocol, security, default_port) = match value("FSProtocol").as_deref().unwrap_or("2") {
+    // SCP sessions are served over the same SSH connection as SFTP.
+    "0" | "1" | "2" => (Protocol::Sftp, FtpSecurity::None, 22),
+    "5" => match value("Ftps").as_deref().unwrap_or("0") {
+      "1" => (Protocol::Ftp, FtpSecurity::Implicit, 990),
+      "2" | "3" => (Protocol::Ftp, FtpSecurity::Explicit, 21),
+      _ => (Protocol::Ftp, FtpSecurity::None, 21),
+    },
+    other => {
+      parsed
+        .warnings
+        .push(format!("{}: unsupported WinSCP protocol {}", name, other));
+      return;
+    }
+  };
+
+  let username = value("UserName").unwrap_or_default();
+  let password = match values.get("Password") {
+    Some(encrypted) if !master_password => {
+      let key = format!("{}{}", username, host);
+      let password = decrypt_password(encrypted, &key);
+      if password.is_none() {
+        parsed
+          .warnings
+          .push(format!("{}: unable to read the saved password", name));
+      }
+      password
+    }
+    _ => None,
+  };
+
+  let profile = SiteProfile {
+    name,
+    folder: if segments.is_empty() {
+      None
+    } else {
+      Some(segments.join("/"))
+    },
+    port: value("PortNumber")
+      .and_then(|port| port.parse().ok())
+      .unwrap_or(default_port),
+    host,
+    username,
+    protocol,
+    security,
+    remote_dir: value("RemoteDirectory"),
+    local_dir: value("LocalDirectory"),
+    transfer_mode: match value("FtpPasvMode").as_deref() {
+      Some("0") => TransferMode::Active,
+      _ => TransferMode::Passive,
+    },
+    options: SiteOptions {
+      notes: value("Note").unwrap_or_default(),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  parsed.sites.push(ImportedSite {
+    profile,
+    password: password.filter(|password| !password.is_empty()),
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use std::path::Path;
+
+  /// `tests/fixtures/sites/WinSCP.ini` holds sessions as WinSCP saves them,
+  /// with passwords for `s3cr3t!`, `pa ss=wörd` and, in the older unkeyed
+  /// form, `legacy`.
+  fn fixture() -> String {
+    fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sites/WinSCP.ini")).unwrap()
+  }
+
+  #[test]
+  fn imports_winscp_fixture() {
+    let parsed = parse(&fixture()).unwrap();
+    assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+    assert_eq!(parsed.sites.len(), 3);
+
+    let deploy = &parsed.sites[0];
+    assert_eq!(deploy.profile.name, "Deploy FTP");
+    assert_eq!(deploy.profile.folder.as_deref(), Some("Clients/Acme"));
+    assert_eq!(deploy.profile.host, "ftp.example.com");
+    assert_eq!(deploy.profile.port, 2121);
+    assert_eq!(deploy.profile.protocol, Protocol::Ftp);
+    assert_eq!(deploy.profile.security, FtpSecurity::Explicit);
+    assert_eq!(deploy.profile.transfer_mode, TransferMode::Active);
+    assert_eq!(deploy.profile.remote_dir.as_deref(), Some("/var/www"));
+    assert_eq!(deploy.password.as_deref(), Some("s3cr3t!"));
+
+    let backup = &parsed.sites[1];
+    assert_eq!(backup.profile.protocol, Protocol::Sftp);
+    assert_eq!(backup.profile.port, 22);
+    assert_eq!(backup.profile.remote_dir.as_deref(), Some("/srv/backup files"));
+    assert_eq!(backup.profile.options.notes, "Nightly dumps");
+    assert_eq!(backup.password.as_deref(), Some("pa ss=wörd"));
+
+    let legacy = &parsed.sites[2];
+    assert_eq!(legacy.profile.protocol, Protocol::Sftp);
+    assert_eq!(legacy.password.as_deref(), Some("legacy"));
+  }
+
+  #[test]
+  fn decrypts_flagged_and_unflagged_passwords() {
+    let flagged = "A35C4056119644796CE741314F7038392C3033253A282C7239243D312C3039723F33312F6F3F2E6F287D828A7F2772854263";
+    assert_eq!(decrypt_password(flagged, "deployftp.example.com").as_deref(), Some("s3cr3t!"));
+    // The flagged form embeds the key, so another user or host reads nothing.
+    assert_eq!(decrypt_password(flagged, "otherftp.example.com"), None);
+
+    let unflagged = "5A793CE26D7C4235A286FCB2B4E5C523002075C5A1F3B9CF79608A08F311A68B487BFCF2EFA2B530393B3D3F257F73D6AE7D";
+    assert_eq!(decrypt_password(unflagged, "any key").as_deref(), Some("legacy"));
+
+    let empty = "A35C4F5DC23D3233323A282C7239243D312C303972323928B8CD99ED57B0E90A67A04233CF1E229794A27509B991D21A80D2";
+    assert_eq!(decrypt_password(empty, "anonftp.example.net").as_deref(), Some(""));
+  }
+
+  #[test]
+  fn rejects_malformed_passwords() {
+    assert_eq!(decrypt_password("A35", "key"), None);
+    assert_eq!(decrypt_password("ZZ5C", "key"), None);
+    // Announces 28 bytes after a shift of 10, then ends.
+    assert_eq!(decrypt_password("A35C4056", "key"), None);
+  }
+
+  #[test]
+  fn skips_passwords_behind_a_master_password() {
+    let raw = fixture().replace("UseMasterPassword=0", "UseMasterPassword=1");
+    let parsed = parse(&raw).unwrap();
+    assert_eq!(parsed.sites.len(), 3);
+    assert!(parsed.sites.iter().all(|site| site.password.is_none()));
+    assert_eq!(parsed.warnings.len(), 1);
+  }
+}
@@ -0,0 +1,728 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{Manager, Window};
+
+use crate::error::{AppError, ErrorKind};
+use crate::remote::RemoteFs;
+use crate::session::Session;
+use crate::verify::{checksums_match, hex, local_checksum, VerifyMethod};
+use crate::{
+  download_with, emit_batch, join_remote, log_event, map_err, upload_with, walk_local, walk_remote, AppState, RecursiveEntry,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncDirection {
+  /// Make the remote tree match the local one.
+  MirrorUp,
+  /// Make the local tree match the remote one.
+  MirrorDown,
+  /// Copy the newer side of every difference both ways. Without a record of
+  /// earlier runs a file missing on one side is copied, never deleted.
+  TwoWay,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompareMode {
+  Size,
+  /// Size, then modification time within the tolerance.
+  #[default]
+  SizeAndTime,
+  /// Checksums of both sides for files of equal size, computed by the server
+  /// where it offers one. Otherwise every such remote file is read in full.
+  Checksum,
+}
+
+fn default_tolerance() -> u64 {
+  // LIST only reports minutes.
+  60
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncOptions {
+  local_root: String,
+  remote_root: String,
+  direction: SyncDirection,
+  #[serde(default)]
+  compare: CompareMode,
+  /// Mirror modes delete files that only exist on the target side.
+  #[serde(default)]
+  delete_extraneous: bool,
+  #[serde(default = "default_tolerance")]
+  time_tolerance_secs: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncAction {
+  MkdirLocal,
+  MkdirRemote,
+  Upload,
+  Download,
+  DeleteLocal,
+  DeleteRemote,
+  /// Both sides changed, or the two sides disagree on file versus folder.
+  /// Conflicts are reported and left alone.
+  Conflict,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStep {
+  action: SyncAction,
+  relative_path: String,
+  is_dir: bool,
+  local_size: Option<u64>,
+  remote_size: Option<u64>,
+  local_modified: Option<i64>,
+  remote_modified: Option<i64>,
+  reason: String,
+}
+
+/// The dry-run result. The frontend shows it for review and hands it back,
+/// possibly with steps removed, to `sync_execute`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPlan {
+  local_root: String,
+  remote_root: String,
+  steps: Vec<SyncStep>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncFailure {
+  relative_path: String,
+  message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+  completed: usize,
+  /// Conflicts, and the steps left when the run was cancelled.
+  skipped: usize,
+  failed: Vec<SyncFailure>,
+  cancelled: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SyncProgress {
+  id: String,
+  index: usize,
+  total: usize,
+  relative_path: String,
+  action: SyncAction,
+}
+
+/// Whether a local and a remote file hold the same bytes.
+fn same_content(remote: &mut dyn RemoteFs, local: &Path, remote_path: &str) -> Result<bool, AppError> {
+  // A server that advertises a checksum but fails to compute it is treated
+  // like one without, as in `verify_transfer`.
+  if let Ok(Some((method, remote_sum))) = remote.checksum(remote_path) {
+    return Ok(checksums_match(method, &local_checksum(local, method)?, &remote_sum));
+  }
+  let mut hasher = Sha256::new();
+  remote.get(remote_path, 0, &mut |reader| std::io::copy(reader, &mut hasher))?;
+  Ok(hex(&hasher.finalize()) == local_checksum(local, VerifyMethod::Sha256)?)
+}
+
+fn local_path(root: &str, relative: &str) -> PathBuf {
+  Path::new(root).join(relative)
+}
+
+fn step(action: SyncAction, path: &str, local: Option<&RecursiveEntry>, remote: Option<&RecursiveEntry>, reason: &str) -> SyncStep {
+  SyncStep {
+    action,
+    relative_path: path.to_string(),
+    is_dir: local.or(remote).map(|entry| entry.is_dir).unwrap_or(false),
+    local_size: local.and_then(|entry| entry.size),
+    remote_size: remote.and_then(|entry| entry.size),
+    local_modified: local.and_then(|entry| entry.modified),
+    remote_modified: remote.and_then(|entry| entry.modified),
+    reason: reason.to_string(),
+  }
+}
+
+fn root_step(action: SyncAction) -> SyncStep {
+  SyncStep {
+    action,
+    relative_path: String::new(),
+    is_dir: true,
+    local_size: None,
+    remote_size: None,
+    local_modified: None,
+    remote_modified: None,
+    reason: "Folder does not exist".to_string(),
+  }
+}
+
+/// Which side of a pair of files holds the newer content, if they differ.
+#[derive(PartialEq, Eq)]
+enum Newer {
+  Same,
+  Local,
+  Remote,
+  Unknown,
+}
+
+fn compare_times(local: Option<i64>, remote: Option<i64>, tolerance_secs: u64) -> Newer {
+  let tolerance = tolerance_secs as i64 * 1000;
+  match (local, remote) {
+    (Some(local), Some(remote)) if local - remote > tolerance => Newer::Local,
+    (Some(local), Some(remote)) if remote - local > tolerance => Newer::Remote,
+    (Some(_), Some(_)) => Newer::Same,
+    _ => Newer::Unknown,
+  }
+}
+
+/// `same_content` is only asked in checksum mode, for files of equal size.
+fn compare_files(
+  options: &SyncOptions,
+  path: &str,
+  local_entry: &RecursiveEntry,
+  remote_entry: &RecursiveEntry,
+  same_content: &mut dyn FnMut(&str) -> Result<bool, AppError>,
+) -> Result<Newer, AppError> {
+  let by_time = compare_times(local_entry.modified, remote_entry.modified, options.time_tolerance_secs);
+  let size_differs = local_entry.size != remote_entry.size;
+  let content_differs = match options.compare {
+    CompareMode::Size => size_differs,
+    CompareMode::SizeAndTime => size_differs || matches!(by_time, Newer::Local | Newer::Remote),
+    CompareMode::Checksum => size_differs || !same_content(path)?,
+  };
+  if !content_differs {
+    return Ok(Newer::Same);
+  }
+  Ok(match by_time {
+    Newer::Same => Newer::Unknown,
+    other => other,
+  })
+}
+
+/// Lists both trees and works out the steps that bring them in line.
+/// Folders are created first, then files move, then deletions run deepest
+/// first.
+pub fn plan_sync(remote: &mut dyn RemoteFs, options: &SyncOptions) -> Result<SyncPlan, AppError> {
+  // A missing root is an empty tree, created by the first step if needed.
+  // Any other failure must not pass for one, or every file would be planned
+  // as missing on the server.
+  let local_exists = Path::new(&options.local_root).is_dir();
+  let remote_exists = match remote.stat(&options.remote_root) {
+    Ok(stat) => stat.is_dir,
+    Err(err) if err.kind == ErrorKind::NotFound => false,
+    Err(err) => return Err(err),
+  };
+  let local: BTreeMap<String, RecursiveEntry> = if local_exists {
+    walk_local(&options.local_root)?
+  } else {
+    Vec::new()
+  }
+  .into_iter()
+  .map(|entry| (entry.relative_path.clone(), entry))
+  .collect();
+  let remote_tree: BTreeMap<String, RecursiveEntry> = if remote_exists {
+    walk_remote(remote, &options.remote_root)?
+  } else {
+    Vec::new()
+  }
+  .into_iter()
+  .map(|entry| (entry.relative_path.clone(), entry))
+  .collect();
+  let steps = plan_steps(options, &local, &remote_tree, local_exists, remote_exists, &mut |path| {
+    same_content(
+      remote,
+      &local_path(&options.local_root, path),
+      &join_remote(&options.remote_root, path),
+    )
+  })?;
+  Ok(SyncPlan {
+    local_root: options.local_root.clone(),
+    remote_root: options.remote_root.clone(),
+    steps,
+  })
+}
+
+/// The planning itself, apart from the listings.
+fn plan_steps(
+  options: &SyncOptions,
+  local: &BTreeMap<String, RecursiveEntry>,
+  remote_tree: &BTreeMap<String, RecursiveEntry>,
+  local_exists: bool,
+  remote_exists: bool,
+  same_content: &mut dyn FnMut(&str) -> Result<bool, AppError>,
+) -> Result<Vec<SyncStep>, AppError> {
+  let mut paths: Vec<&String> = local.keys().chain(remote_tree.keys()).collect();
+  paths.sort();
+  paths.dedup();
+
+  let direction = options.direction;
+  let mut mkdirs = Vec::new();
+  if !remote_exists && direction != SyncDirection::MirrorDown {
+    mkdirs.push(root_step(SyncAction::MkdirRemote));
+  }
+  if !local_exists && direction != SyncDirection::MirrorUp {
+    mkdirs.push(root_step(SyncAction::MkdirLocal));
+  }
+  let mut transfers = Vec::new();
+  let mut deletes = Vec::new();
+  for path in paths {
+    let local_entry = local.get(path);
+    let remote_entry = remote_tree.get(path);
+    match (local_entry, remote_entry) {
+      (Some(l), Some(r)) if l.is_dir != r.is_dir => {
+        transfers.push(step(SyncAction::Conflict, path, Some(l), Some(r), "File on one side, folder on the other"));
+      }
+      (Some(l), Some(_)) if l.is_dir => {}
+      (Some(l), Some(r)) => {
+        let newer = compare_files(options, path, l, r, same_content)?;
+        let action = match (direction, newer) {
+          (_, Newer::Same) => continue,
+          (SyncDirection::MirrorUp, _) => Some((SyncAction::Upload, "Differs from local")),
+          (SyncDirection::MirrorDown, _) => Some((SyncAction::Download, "Differs from remote")),
+          (SyncDirection::TwoWay, Newer::Local) => Some((SyncAction::Upload, "Local copy is newer")),
+          (SyncDirection::TwoWay, Newer::Remote) => Some((SyncAction::Download, "Remote copy is newer")),
+          (SyncDirection::TwoWay, Newer::Unknown) => None,
+        };
+        match action {
+          Some((action, reason)) => transfers.push(step(action, path, Some(l), Some(r), reason)),
+          None => transfers.push(step(SyncAction::Conflict, path, Some(l), Some(r), "Both sides differ")),
+        }
+      }
+      (Some(l), None) => {
+        if direction == SyncDirection::MirrorDown {
+          if options.delete_extraneous {
+            deletes.push(step(SyncAction::DeleteLocal, path, Some(l), None, "Not on remote"));
+          }
+        } else if l.is_dir {
+          mkdirs.push(step(SyncAction::MkdirRemote, path, Some(l), None, "Missing on remote"));
+        } else {
+          transfers.push(step(SyncAction::Upload, path, Some(l), None, "Missing on remote"));
+        }
+      }
+      (None, Some(r)) => {
+        if direction == SyncDirection::MirrorUp {
+          if options.delete_extraneous {
+            deletes.push(step(SyncAction::DeleteRemote, path, None, Some(r), "Not on local"));
+          }
+        } else if r.is_dir {
+          mkdirs.push(step(SyncAction::MkdirLocal, path, None, Some(r), "Missing locally"));
+        } else {
+          transfers.push(step(SyncAction::Download, path, None, Some(r), "Missing locally"));
+        }
+      }
+      (None, None) => {}
+    }
+  }
+  deletes.reverse();
+
+  let mut steps = mkdirs;
+  steps.append(&mut transfers);
+  steps.append(&mut deletes);
+  Ok(steps)
+}
+
+impl SyncStep {
+  /// Bytes moved by an upload or download step.
+  fn transfer_size(&self) -> Option<u64> {
+    match self.action {
+      SyncAction::Upload => self.local_size,
+      SyncAction::Download => self.remote_size,
+      _ => None,
+    }
+  }
+
+  fn is_transfer(&self) -> bool {
+    matches!(self.action, SyncAction::Upload | SyncAction::Download)
+  }
+
+  fn uses_remote(&self) -> bool {
+    matches!(
+      self.action,
+      SyncAction::MkdirRemote | SyncAction::Upload | SyncAction::Download | SyncAction::DeleteRemote
+    )
+  }
+}
+
+fn run_step(remote: &mut dyn RemoteFs, window: &Window, id: &str, plan: &SyncPlan, step: &SyncStep) -> Result<(), AppError> {
+  let local = local_path(&plan.local_root, &step.relative_path);
+  let local_str = local.to_string_lossy().to_string();
+  let remote_path = if step.relative_path.is_empty() {
+    plan.remote_root.clone()
+  } else {
+    join_remote(&plan.remote_root, &step.relative_path)
+  };
+  match step.action {
+    SyncAction::MkdirRemote => remote.mkdir(&remote_path).or_else(|err| {
+      // Already there is fine.
+      match remote.stat(&remote_path) {
+        Ok(stat) if stat.is_dir => Ok(()),
+        _ => Err(err),
+      }
+    }),
+    SyncAction::Upload => upload_with(remote, None, window, id, &local_str, &remote_path, 0),
+    SyncAction::Download => download_with(remote, window, id, &remote_path, &local_str),
+    SyncAction::DeleteRemote if step.is_dir => remote.rmdir(&remote_path),
+    SyncAction::DeleteRemote => remote.rm(&remote_path),
+    SyncAction::MkdirLocal | SyncAction::DeleteLocal | SyncAction::Conflict => run_local_step(plan, step),
+  }
+}
+
+/// Steps that leave the server alone.
+fn run_local_step(plan: &SyncPlan, step: &SyncStep) -> Result<(), AppError> {
+  let local = local_path(&plan.local_root, &step.relative_path);
+  match step.action {
+    SyncAction::MkdirLocal => fs::create_dir_all(&local).map_err(map_err),
+    SyncAction::DeleteLocal if step.is_dir => fs::remove_dir(&local).map_err(map_err),
+    SyncAction::DeleteLocal => fs::remove_file(&local).map_err(map_err),
+    _ => Ok(()),
+  }
+}
+
+/// Carries out a reviewed plan. Failed steps are reported and do not stop the
+/// rest; conflicts are skipped. A worker is dropped after a failed step, as
+/// `checkin_worker` asks, and the next remote step logs in a fresh one.
+/// Cancelling `id` stops the transfer in progress and skips the steps left.
+pub fn execute_sync(session: &Session, window: &Window, id: &str, plan: &SyncPlan) -> SyncReport {
+  let cancels = &window.state::<AppState>().cancels;
+  let mut report = SyncReport::default();
+  let total = plan.steps.len();
+  let files_total = plan.steps.iter().filter(|step| step.is_transfer()).count();
+  let bytes_total = plan
+    .steps
+    .iter()
+    .filter(|step| step.is_transfer())
+    .map(SyncStep::transfer_size)
+    .sum::<Option<u64>>();
+  let mut files_done = 0;
+  let mut bytes_done = 0;
+  let mut worker: Option<Box<dyn RemoteFs>> = None;
+  for (index, step) in plan.steps.iter().enumerate() {
+    if cancels.is_cancelled(id) {
+      report.skipped += total - index;
+      report.cancelled = true;
+      break;
+    }
+    let _ = window.emit(
+      "sync-progress",
+      SyncProgress {
+        id: id.to_string(),
+        index,
+        total,
+        relative_path: step.relative_path.clone(),
+        action: step.action,
+      },
+    );
+    if step.action == SyncAction::Conflict {
+      report.skipped += 1;
+      continue;
+    }
+    let result = if step.uses_remote() {
+      match worker.take().map_or_else(|| session.checkout_worker(window), Ok) {
+        Ok(mut remote) => {
+          let result = run_step(remote.as_mut(), window, id, plan, step);
+          if result.is_ok() {
+            worker = Some(remote);
+          }
+          result
+        }
+        Err(err) => Err(err),
+      }
+    } else {
+      run_local_step(plan, step)
+    };
+    if step.is_transfer() {
+      // Failed files count as finished so the batch still reaches its end.
+      files_done += 1;
+      bytes_done += step.transfer_size().unwrap_or(0);
+      emit_batch(window, id, (files_done, files_total), bytes_done, bytes_total);
+    }
+    match result {
+      Ok(_) => report.completed += 1,
+      Err(err) if err.kind == ErrorKind::Cancelled => report.skipped += 1,
+      Err(err) => {
+        log_event(window, "error", format!("Sync failed for {}: {}", step.relative_path, err));
+        report.failed.push(SyncFailure {
+          relative_path: step.relative_path.clone(),
+          message: err.message,
+        });
+      }
+    }
+  }
+  if let Some(worker) = worker {
+    session.checkin_worker(worker);
+  }
+  log_event(
+    window,
+    if report.cancelled {
+      "info"
+    } else if report.failed.is_empty() {
+      "success"
+    } else {
+      "error"
+    },
+    format!(
+      "Sync {}: {} done, {} skipped, {} failed",
+      if report.cancelled { "cancelled" } else { "finished" },
+      report.completed,
+      report.skipped,
+      report.failed.len()
+    ),
+  );
+  report
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const MINUTE: i64 = 60_000;
+  const NOON: i64 = 1_718_452_800_000;
+
+  fn file(path: &str, size: u64, modified: Option<i64>) -> RecursiveEntry {
+    RecursiveEntry {
+      relative_path: path.to_string(),
+      is_dir: false,
+      size: Some(size),
+      modified,
+    }
+  }
+
+  fn dir(path: &str) -> RecursiveEntry {
+    RecursiveEntry {
+      relative_path: path.to_string(),
+      is_dir: true,
+      size: None,
+      modified: None,
+    }
+  }
+
+  fn options(direction: SyncDirection) -> SyncOptions {
+    SyncOptions {
+      local_root: "/local".to_string(),
+      remote_root: "/remote".to_string(),
+      direction,
+      compare: CompareMode::SizeAndTime,
+      delete_extraneous: false,
+      time_tolerance_secs: default_tolerance(),
+    }
+  }
+
+  fn tree(entries: Vec<RecursiveEntry>) -> BTreeMap<String, RecursiveEntry> {
+    entries
+      .into_iter()
+      .map(|entry| (entry.relative_path.clone(), entry))
+      .collect()
+  }
+
+  /// Plans with both roots present and checksums reported equal.
+  fn plan(options: &SyncOptions, local: Vec<RecursiveEntry>, remote: Vec<RecursiveEntry>) -> Vec<(SyncAction, String)> {
+    plan_steps(options, &tree(local), &tree(remote), true, true, &mut |_| Ok(true))
+      .unwrap()
+      .into_iter()
+      .map(|step| (step.action, step.relative_path))
+      .collect()
+  }
+
+  fn one(action: SyncAction, path: &str) -> Vec<(SyncAction, String)> {
+    vec![(action, path.to_string())]
+  }
+
+  const ALL: [SyncDirection; 3] = [SyncDirection::MirrorUp, SyncDirection::MirrorDown, SyncDirection::TwoWay];
+
+  #[test]
+  fn leaves_equal_files_alone() {
+    for direction in ALL {
+      let steps = plan(
+        &options(direction),
+        vec![file("a.txt", 10, Some(NOON))],
+        vec![file("a.txt", 10, Some(NOON))],
+      );
+      assert!(steps.is_empty(), "{:?}: {:?}", direction, steps);
+    }
+  }
+
+  #[test]
+  fn ignores_time_differences_within_the_tolerance() {
+    for direction in ALL {
+      let steps = plan(
+        &options(direction),
+        vec![file("a.txt", 10, Some(NOON + MINUTE))],
+        vec![file("a.txt", 10, Some(NOON))],
+      );
+      assert!(steps.is_empty(), "{:?}: {:?}", direction, steps);
+    }
+  }
+
+  #[test]
+  fn copies_the_newer_side() {
+    let local_newer = (vec![file("a.txt", 10, Some(NOON + 5 * MINUTE))], vec![file("a.txt", 10, Some(NOON))]);
+    let remote_newer = (vec![file("a.txt", 10, Some(NOON))], vec![file("a.txt", 12, Some(NOON + 5 * MINUTE))]);
+    let cases = [
+      (SyncDirection::MirrorUp, &local_newer, SyncAction::Upload),
+      (SyncDirection::MirrorUp, &remote_newer, SyncAction::Upload),
+      (SyncDirection::MirrorDown, &local_newer, SyncAction::Download),
+      (SyncDirection::MirrorDown, &remote_newer, SyncAction::Download),
+      (SyncDirection::TwoWay, &local_newer, SyncAction::Upload),
+      (SyncDirection::TwoWay, &remote_newer, SyncAction::Download),
+    ];
+    for (direction, (local, remote), action) in cases {
+      let steps = plan(&options(direction), local.clone(), remote.clone());
+      assert_eq!(steps, one(action, "a.txt"), "{:?}", direction);
+    }
+  }
+
+  #[test]
+  fn two_way_reports_a_conflict_when_it_cannot_tell_which_is_newer() {
+    // Sizes differ but the times are within the tolerance, or unknown.
+    let cases = [
+      (Some(NOON + MINUTE), Some(NOON)),
+      (None, Some(NOON)),
+      (Some(NOON), None),
+    ];
+    for (local_time, remote_time) in cases {
+      let local = vec![file("a.txt", 10, local_time)];
+      let remote = vec![file("a.txt", 20, remote_time)];
+      let two_way = plan(&options(SyncDirection::TwoWay), local.clone(), remote.clone());
+      assert_eq!(two_way, one(SyncAction::Conflict, "a.txt"));
+      let up = plan(&options(SyncDirection::MirrorUp), local.clone(), remote.clone());
+      assert_eq!(up, one(SyncAction::Upload, "a.txt"));
+      let down = plan(&options(SyncDirection::MirrorDown), local, remote);
+      assert_eq!(down, one(SyncAction::Download, "a.txt"));
+    }
+  }
+
+  #[test]
+  fn unknown_times_with_equal_sizes_count_as_equal() {
+    for direction in ALL {
+      let steps = plan(&options(direction), vec![file("a.txt", 10, None)], vec![file("a.txt", 10, Some(NOON))]);
+      assert!(steps.is_empty(), "{:?}: {:?}", direction, steps);
+    }
+  }
+
+  #[test]
+  fn checksum_mode_compares_content_of_equal_sizes() {
+    let mut options = options(SyncDirection::TwoWay);
+    options.compare = CompareMode::Checksum;
+    let local = tree(vec![file("a.txt", 10, Some(NOON + 5 * MINUTE)), file("b.txt", 10, Some(NOON))]);
+    let remote = tree(vec![file("a.txt", 10, Some(NOON)), file("b.txt", 10, Some(NOON + 5 * MINUTE))]);
+    let mut asked = Vec::new();
+    let steps = plan_steps(&options, &local, &remote, true, true, &mut |path| {
+      asked.push(path.to_string());
+      Ok(path == "b.txt")
+    })
+    .unwrap();
+    assert_eq!(asked, ["a.txt", "b.txt"]);
+    let steps: Vec<_> = steps.into_iter().map(|step| (step.action, step.relative_path)).collect();
+    assert_eq!(steps, one(SyncAction::Upload, "a.txt"));
+  }
+
+  #[test]
+  fn copies_missing_files_and_deletes_only_when_asked() {
+    let local = vec![dir("docs"), file("docs/new.txt", 1, Some(NOON))];
+    let remote = vec![dir("old"), file("old/gone.txt", 1, Some(NOON))];
+
+    let two_way = plan(&options(SyncDirection::TwoWay), local.clone(), remote.clone());
+    assert_eq!(
+      two_way,
+      vec![
+        (SyncAction::MkdirRemote, "docs".to_string()),
+        (SyncAction::MkdirLocal, "old".to_string()),
+        (SyncAction::Upload, "docs/new.txt".to_string()),
+        (SyncAction::Download, "old/gone.txt".to_string()),
+      ]
+    );
+
+    let mut up = options(SyncDirection::MirrorUp);
+    assert_eq!(
+      plan(&up, local.clone(), remote.clone()),
+      vec![
+        (SyncAction::MkdirRemote, "docs".to_string()),
+        (SyncAction::Upload, "docs/new.txt".to_string()),
+      ]
+    );
+    up.delete_extraneous = true;
+    assert_eq!(
+      plan(&up, local, remote),
+      vec![
+        (SyncAction::MkdirRemote, "docs".to_string()),
+        (SyncAction::Upload, "docs/new.txt".to_string()),
+        (SyncAction::DeleteRemote, "old/gone.txt".to_string()),
+        (SyncAction::DeleteRemote, "old".to_string()),
+      ]
+    );
+  }
+
+  /// A server whose root `stat` fails with the given error.
+  struct FailingStat(ErrorKind);
+
+  impl RemoteFs for FailingStat {
+    fn pwd(&mut self) -> Result<String, AppError> {
+      unreachable!()
+    }
+    fn cwd(&mut self, _path: &str) -> Result<(), AppError> {
+      unreachable!()
+    }
+    fn list(&mut self, _path: Option<&str>) -> Result<Vec<crate::FtpEntry>, AppError> {
+      unreachable!()
+    }
+    fn stat(&mut self, _path: &str) -> Result<crate::remote::RemoteStat, AppError> {
+      Err(AppError::new(self.0, "stat failed"))
+    }
+    fn get(&mut self, _path: &str, _offset: u64, _sink: &mut crate::remote::ReadSink<'_>) -> Result<u64, AppError> {
+      unreachable!()
+    }
+    fn put(&mut self, _path: &str, _offset: u64, _reader: &mut dyn std::io::Read) -> Result<u64, AppError> {
+      unreachable!()
+    }
+    fn mkdir(&mut self, _path: &str) -> Result<(), AppError> {
+      unreachable!()
+    }
+    fn rm(&mut self, _path: &str) -> Result<(), AppError> {
+      unreachable!()
+    }
+    fn rmdir(&mut self, _path: &str) -> Result<(), AppError> {
+      unreachable!()
+    }
+    fn rename(&mut self, _from: &str, _to: &str) -> Result<(), AppError> {
+      unreachable!()
+    }
+    fn quit(&mut self) {}
+    fn noop(&mut self) -> Result<(), AppError> {
+      unreachable!()
+    }
+  }
+
+  #[test]
+  fn only_a_missing_remote_root_plans_as_empty() {
+    let root = std::env::temp_dir().join(format!("sync-root-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("keep.txt"), b"data").unwrap();
+    let mut options = options(SyncDirection::MirrorDown);
+    options.local_root = root.to_string_lossy().to_string();
+    options.delete_extraneous = true;
+
+    for kind in [ErrorKind::Timeout, ErrorKind::NotConnected, ErrorKind::AuthFailed] {
+      let err = plan_sync(&mut FailingStat(kind), &options).unwrap_err();
+      assert_eq!(err.kind, kind);
+    }
+    let plan = plan_sync(&mut FailingStat(ErrorKind::NotFound), &options).unwrap();
+    let steps: Vec<_> = plan.steps.into_iter().map(|step| (step.action, step.relative_path)).collect();
+    assert_eq!(steps, one(SyncAction::DeleteLocal, "keep.txt"));
+    fs::remove_dir_all(&root).unwrap();
+  }
+
+  #[test]
+  fn reports_a_file_facing_a_folder() {
+    for direction in ALL {
+      let steps = plan(&options(direction), vec![file("a", 1, Some(NOON))], vec![dir("a")]);
+      assert_eq!(steps, one(SyncAction::Conflict, "a"), "{:?}", direction);
+    }
+  }
+}
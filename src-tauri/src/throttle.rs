@@ -0,0 +1,290 @@
+use chrono::{Datelike, Local, Timelike};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::AppError;
+use crate::queue::TransferDirection;
+use crate::map_err;
+
+/// Caps in kilobytes (1024 bytes) per second, matching the frontend's
+/// `uploadLimitKbps`/`downloadLimitKbps`. Zero means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RateLimits {
+  pub upload_kbps: u64,
+  pub download_kbps: u64,
+}
+
+impl RateLimits {
+  fn bytes_per_sec(&self, direction: TransferDirection) -> u64 {
+    match direction {
+      TransferDirection::Upload => self.upload_kbps * 1024,
+      TransferDirection::Download => self.download_kbps * 1024,
+    }
+  }
+}
+
+/// Replaces the global caps during a daily time window, e.g. unlimited at
+/// night and capped during office hours.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ThrottleSchedule {
+  /// Days the window applies to, 0 = Sunday as in `Date#getDay`. Empty means
+  /// every day.
+  pub days: Vec<u8>,
+  /// Minutes after local midnight. A window whose end is not after its start
+  /// runs past midnight.
+  pub start_minute: u16,
+  pub end_minute: u16,
+  pub limits: RateLimits,
+}
+
+impl ThrottleSchedule {
+  fn contains(&self, day: u8, minute: u16) -> bool {
+    let day_matches = |day: u8| self.days.is_empty() || self.days.contains(&day);
+    if self.start_minute < self.end_minute {
+      day_matches(day) && minute >= self.start_minute && minute < self.end_minute
+    } else if minute >= self.start_minute {
+      day_matches(day)
+    } else {
+      // After midnight the window belongs to the previous day's schedule.
+      minute < self.end_minute && day_matches((day + 6) % 7)
+    }
+  }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ThrottleConfig {
+  /// Shared by all running transfers in each direction.
+  pub global: RateLimits,
+  /// Applied to every single transfer on top of the global caps.
+  pub per_transfer: RateLimits,
+  /// The first matching window overrides `global`.
+  pub schedules: Vec<ThrottleSchedule>,
+}
+
+impl ThrottleConfig {
+  /// Resolves the schedules against the system's local time, so windows
+  /// follow daylight saving changes.
+  fn global_now(&self) -> RateLimits {
+    if self.schedules.is_empty() {
+      return self.global;
+    }
+    let now = Local::now();
+    let day = now.weekday().num_days_from_sunday() as u8;
+    let minute = (now.hour() * 60 + now.minute()) as u16;
+    self.global_at(day, minute)
+  }
+
+  fn global_at(&self, day: u8, minute: u16) -> RateLimits {
+    self
+      .schedules
+      .iter()
+      .find(|schedule| schedule.contains(day, minute))
+      .map(|schedule| schedule.limits)
+      .unwrap_or(self.global)
+  }
+}
+
+/// Allows a one-second burst and goes into debt for larger reads, which the
+/// caller pays off by sleeping.
+#[derive(Debug)]
+pub struct TokenBucket {
+  tokens: f64,
+  last: Instant,
+}
+
+impl Default for TokenBucket {
+  fn default() -> Self {
+    Self {
+      tokens: 0.0,
+      last: Instant::now(),
+    }
+  }
+}
+
+impl TokenBucket {
+  /// Takes `amount` bytes at `rate` bytes per second and returns how long to
+  /// wait before continuing.
+  fn take(&mut self, rate: u64, amount: usize) -> Duration {
+    self.take_at(Instant::now(), rate, amount)
+  }
+
+  fn take_at(&mut self, now: Instant, rate: u64, amount: usize) -> Duration {
+    let elapsed = now.duration_since(self.last).as_secs_f64();
+    self.last = now;
+    if rate == 0 {
+      self.tokens = 0.0;
+      return Duration::ZERO;
+    }
+    let rate = rate as f64;
+    self.tokens = (self.tokens + elapsed * rate).min(rate) - amount as f64;
+    if self.tokens >= 0.0 {
+      Duration::ZERO
+    } else {
+      Duration::from_secs_f64(-self.tokens / rate)
+    }
+  }
+}
+
+/// Longest single sleep, so a throttled transfer notices a cancel quickly.
+const SLEEP_SLICE: Duration = Duration::from_millis(100);
+
+#[derive(Default)]
+pub struct Throttle {
+  config: Mutex<ThrottleConfig>,
+  upload: Mutex<TokenBucket>,
+  download: Mutex<TokenBucket>,
+}
+
+impl Throttle {
+  pub fn new(config: ThrottleConfig) -> Self {
+    Self {
+      config: Mutex::new(config),
+      ..Default::default()
+    }
+  }
+
+  pub fn config(&self) -> Result<ThrottleConfig, AppError> {
+    Ok(self.config.lock().map_err(map_err)?.clone())
+  }
+
+  /// Takes effect on the next chunk of every running transfer.
+  pub fn set_config(&self, config: ThrottleConfig) -> Result<(), AppError> {
+    *self.config.lock().map_err(map_err)? = config;
+    Ok(())
+  }
+
+  /// Accounts for `amount` bytes moved by one transfer and blocks as long as
+  /// the stricter of the global and per-transfer caps requires, returning
+  /// early once `stopped` reports the transfer was cancelled or paused.
+  pub fn consume(
+    &self,
+    direction: TransferDirection,
+    own: &mut TokenBucket,
+    amount: usize,
+    stopped: impl Fn() -> bool,
+  ) {
+    let (global, per_transfer) = match self.config.lock() {
+      Ok(config) => (
+        config.global_now().bytes_per_sec(direction),
+        config.per_transfer.bytes_per_sec(direction),
+      ),
+      Err(_) => return,
+    };
+    let shared = match direction {
+      TransferDirection::Upload => &self.upload,
+      TransferDirection::Download => &self.download,
+    };
+    let wait_global = shared
+      .lock()
+      .map(|mut bucket| bucket.take(global, amount))
+      .unwrap_or_default();
+    let wait_own = own.take(per_transfer, amount);
+    let deadline = Instant::now() + wait_global.max(wait_own);
+    loop {
+      let left = deadline.saturating_duration_since(Instant::now());
+      if left.is_zero() || stopped() {
+        break;
+      }
+      std::thread::sleep(left.min(SLEEP_SLICE));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SUNDAY: u8 = 0;
+  const FRIDAY: u8 = 5;
+  const SATURDAY: u8 = 6;
+
+  fn at(hour: u16, minute: u16) -> u16 {
+    hour * 60 + minute
+  }
+
+  fn limits(kbps: u64) -> RateLimits {
+    RateLimits {
+      upload_kbps: kbps,
+      download_kbps: kbps,
+    }
+  }
+
+  fn schedule(days: &[u8], start: u16, end: u16, kbps: u64) -> ThrottleSchedule {
+    ThrottleSchedule {
+      days: days.to_vec(),
+      start_minute: start,
+      end_minute: end,
+      limits: limits(kbps),
+    }
+  }
+
+  fn config(schedules: Vec<ThrottleSchedule>) -> ThrottleConfig {
+    ThrottleConfig {
+      global: limits(1),
+      schedules,
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn window_past_midnight_belongs_to_the_day_it_starts() {
+    let config = config(vec![schedule(&[FRIDAY, SATURDAY], at(22, 0), at(6, 0), 50)]);
+    assert_eq!(config.global_at(FRIDAY, at(23, 0)), limits(50));
+    assert_eq!(config.global_at(SATURDAY, at(5, 59)), limits(50));
+    assert_eq!(config.global_at(SATURDAY, at(6, 0)), limits(1));
+    assert_eq!(config.global_at(FRIDAY, at(5, 0)), limits(1));
+    // Saturday night runs into Sunday, wrapping around the week.
+    assert_eq!(config.global_at(SUNDAY, at(1, 0)), limits(50));
+    assert_eq!(config.global_at(SUNDAY, at(22, 0)), limits(1));
+  }
+
+  #[test]
+  fn equal_start_and_end_cover_a_whole_day() {
+    let config = config(vec![schedule(&[FRIDAY], at(8, 0), at(8, 0), 50)]);
+    assert_eq!(config.global_at(FRIDAY, at(8, 0)), limits(50));
+    assert_eq!(config.global_at(SATURDAY, at(7, 59)), limits(50));
+    assert_eq!(config.global_at(SATURDAY, at(8, 0)), limits(1));
+    assert_eq!(config.global_at(FRIDAY, at(7, 59)), limits(1));
+  }
+
+  #[test]
+  fn empty_days_match_every_day() {
+    let config = config(vec![schedule(&[], at(9, 0), at(17, 0), 50)]);
+    for day in 0..7 {
+      assert_eq!(config.global_at(day, at(12, 0)), limits(50));
+      assert_eq!(config.global_at(day, at(17, 0)), limits(1));
+    }
+  }
+
+  #[test]
+  fn first_matching_window_wins() {
+    let config = config(vec![
+      schedule(&[], at(9, 0), at(12, 0), 50),
+      schedule(&[], at(0, 0), at(0, 0), 20),
+    ]);
+    assert_eq!(config.global_at(FRIDAY, at(10, 0)), limits(50));
+    assert_eq!(config.global_at(FRIDAY, at(13, 0)), limits(20));
+  }
+
+  #[test]
+  fn bucket_bursts_one_second_and_charges_debt() {
+    let start = Instant::now();
+    let mut bucket = TokenBucket {
+      tokens: 0.0,
+      last: start,
+    };
+    // Nothing saved up: half a second of debt.
+    assert_eq!(bucket.take_at(start, 1000, 500), Duration::from_millis(500));
+    // Idle time refills at most one second's worth.
+    let later = start + Duration::from_secs(3);
+    assert_eq!(bucket.take_at(later, 1000, 1000), Duration::ZERO);
+    assert_eq!(bucket.take_at(later, 1000, 250), Duration::from_millis(250));
+    // Unlimited never waits and forgets the debt.
+    assert_eq!(bucket.take_at(later, 0, 1 << 20), Duration::ZERO);
+    assert_eq!(bucket.take_at(later, 1000, 100), Duration::from_millis(100));
+  }
+}
@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+use suppaftp::native_tls::{Certificate, TlsConnector, TlsStream};
+use suppaftp::{FtpError, NativeTlsStream, TlsConnector as FtpTlsConnector};
+
+use crate::error::{AppError, ErrorKind};
+use crate::{connect_tcp, map_err};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FtpSecurity {
+  #[default]
+  None,
+  /// Plain connection upgraded with `AUTH TLS` before login.
+  Explicit,
+  /// TLS from the first byte, conventionally on port 990.
+  Implicit,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum CertificateValidation {
+  #[default]
+  System,
+  Pinned {
+    fingerprint: String,
+  },
+  /// Trust the system store, otherwise ask the user. The frontend retries with
+  /// the fingerprint from the `tls-certificate-prompt` event once accepted,
+  /// which from then on is checked like a pinned one.
+  AcceptOnce {
+    #[serde(default)]
+    fingerprint: Option<String>,
+  },
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CertificatePrompt {
+  pub host: String,
+  pub port: u16,
+  pub fingerprint: String,
+  pub reason: String,
+}
+
+/// The connector for one session. With a pinned or accepted certificate the
+/// system trust store is not consulted; instead the SHA-256 fingerprint of the
+/// peer certificate is compared after every handshake, control and data alike.
+#[derive(Clone, Debug)]
+pub struct SessionTls {
+  pub connector: TlsConnector,
+  pub fingerprint: Option<String>,
+}
+
+impl SessionTls {
+  pub fn new(validation: &CertificateValidation) -> Result<Self, AppError> {
+    let fingerprint = match validation {
+      CertificateValidation::System | CertificateValidation::AcceptOnce { fingerprint: None } => None,
+      CertificateValidation::Pinned { fingerprint }
+      | CertificateValidation::AcceptOnce {
+        fingerprint: Some(fingerprint),
+      } => Some(fingerprint.clone()),
+    };
+    // A pinned certificate may be self-signed, expired or issued for another
+    // name; the fingerprint alone decides.
+    let connector = match fingerprint {
+      Some(_) => insecure_connector()?,
+      None => TlsConnector::new().map_err(map_err)?,
+    };
+    Ok(Self {
+      connector,
+      fingerprint,
+    })
+  }
+
+  pub fn connect(&self, host: &str, stream: TcpStream) -> Result<TlsStream<TcpStream>, AppError> {
+    let stream = self.connector.connect(host, stream).map_err(map_err)?;
+    if let Some(expected) = &self.fingerprint {
+      check_fingerprint(&stream, expected)?;
+    }
+    Ok(stream)
+  }
+
+  /// The connector handed to suppaftp, which uses it for the control
+  /// connection and every passive data connection.
+  pub fn ftp_connector(&self) -> CheckedConnector {
+    CheckedConnector(self.clone())
+  }
+}
+
+/// Compares the pinned fingerprint right after each handshake suppaftp makes.
+#[derive(Debug)]
+pub struct CheckedConnector(SessionTls);
+
+impl FtpTlsConnector for CheckedConnector {
+  type Stream = NativeTlsStream;
+
+  fn connect(&self, domain: &str, stream: TcpStream) -> Result<NativeTlsStream, FtpError> {
+    self
+      .0
+      .connect(domain, stream)
+      .map(NativeTlsStream::from)
+      .map_err(|err| FtpError::SecureError(err.message))
+  }
+}
+
+/// Reads one reply, following multi-line replies to their last line.
+pub fn read_reply(reader: &mut impl BufRead) -> Result<(u32, String), AppError> {
+  let mut line = String::new();
+  reader.read_line(&mut line).map_err(map_err)?;
+  let code = line
+    .get(..3)
+    .and_then(|value| value.parse::<u32>().ok())
+    .ok_or_else(|| AppError::from(format!("Invalid server reply: {}", line.trim())))?;
+  let mut text = line.clone();
+  if line.as_bytes().get(3) == Some(&b'-') {
+    let terminator = format!("{} ", code);
+    loop {
+      line.clear();
+      if reader.read_line(&mut line).map_err(map_err)? == 0 {
+        break;
+      }
+      text.push_str(&line);
+      if line.starts_with(&terminator) {
+        break;
+      }
+    }
+  }
+  Ok((code, text.trim().to_string()))
+}
+
+fn negotiate_auth_tls(stream: &TcpStream) -> Result<(), AppError> {
+  let mut reader = BufReader::new(stream);
+  let (code, text) = read_reply(&mut reader)?;
+  if code != 220 {
+    return Err(AppError::reply(code, format!("Unexpected greeting: {}", text)));
+  }
+  let mut writer = stream;
+  writer.write_all(b"AUTH TLS\r\n").map_err(map_err)?;
+  let (code, text) = read_reply(&mut reader)?;
+  if code != 234 {
+    return Err(AppError::reply(code, format!("Server refused AUTH TLS: {}", text)));
+  }
+  Ok(())
+}
+
+fn handshake(
+  host: &str,
+  port: u16,
+  security: FtpSecurity,
+  timeout: Duration,
+  connector: &TlsConnector,
+) -> Result<TlsStream<TcpStream>, AppError> {
+  let stream = connect_tcp(host, port, timeout)?;
+  if security == FtpSecurity::Explicit {
+    negotiate_auth_tls(&stream)?;
+  }
+  connector.connect(host, stream).map_err(map_err)
+}
+
+fn insecure_connector() -> Result<TlsConnector, AppError> {
+  TlsConnector::builder()
+    .danger_accept_invalid_certs(true)
+    .danger_accept_invalid_hostnames(true)
+    .build()
+    .map_err(map_err)
+}
+
+/// Colon-separated upper-case hex SHA-256, as shown in certificate prompts.
+pub fn sha256_fingerprint(bytes: &[u8]) -> String {
+  Sha256::digest(bytes)
+    .iter()
+    .map(|byte| format!("{:02X}", byte))
+    .collect::<Vec<_>>()
+    .join(":")
+}
+
+fn format_fingerprint(certificate: &Certificate) -> Result<String, AppError> {
+  Ok(sha256_fingerprint(&certificate.to_der().map_err(map_err)?))
+}
+
+pub fn fingerprints_match(actual: &str, expected: &str) -> bool {
+  let normalize = |value: &str| -> String {
+    value
+      .chars()
+      .filter(|c| c.is_ascii_hexdigit())
+      .collect::<String>()
+      .to_ascii_uppercase()
+  };
+  normalize(actual) == normalize(expected)
+}
+
+fn presented_certificate(stream: &TlsStream<TcpStream>) -> Result<Certificate, AppError> {
+  stream
+    .peer_certificate()
+    .map_err(map_err)?
+    .ok_or_else(|| AppError::new(ErrorKind::TlsError, "Server did not present a certificate"))
+}
+
+fn fingerprint_mismatch(actual: &str) -> AppError {
+  AppError::new(
+    ErrorKind::TlsError,
+    format!("Certificate fingerprint mismatch (server presented {})", actual),
+  )
+}
+
+/// Fails unless an established stream presents the expected certificate.
+pub fn check_fingerprint(stream: &TlsStream<TcpStream>, expected: &str) -> Result<(), AppError> {
+  let actual = format_fingerprint(&presented_certificate(stream)?)?;
+  if fingerprints_match(&actual, expected) {
+    Ok(())
+  } else {
+    Err(fingerprint_mismatch(&actual))
+  }
+}
+
+/// Handshakes once more without validation to learn the certificate the
+/// server presents, so the user can be asked to accept it. Only runs after
+/// the real connection failed to validate.
+pub fn certificate_prompt(
+  host: &str,
+  port: u16,
+  security: FtpSecurity,
+  timeout: Duration,
+  reason: String,
+) -> Result<CertificatePrompt, AppError> {
+  let connector = insecure_connector()?;
+  let mut stream = handshake(host, port, security, timeout, &connector)?;
+  let fingerprint = format_fingerprint(&presented_certificate(&stream)?)?;
+  let _ = stream.write_all(b"QUIT\r\n");
+  let _ = stream.shutdown();
+  Ok(CertificatePrompt {
+    host: host.to_string(),
+    port,
+    fingerprint,
+    reason,
+  })
+}
@@ -0,0 +1,306 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64_ENGINE;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::error::{AppError, ErrorKind};
+use crate::map_err;
+
+const KEYRING_SERVICE: &str = "Ender Transfer";
+const VAULT_FILE: &str = "vault.json";
+const VAULT_VERSION: u32 = 1;
+/// Encrypted on creation so a wrong master password is detected on unlock
+/// instead of producing garbage secrets.
+const CHECK_VALUE: &[u8] = b"ender-transfer-vault";
+
+const KEYRING_PREFIX: &str = "keyring:";
+const FILE_PREFIX: &str = "vault:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VaultBackend {
+  Keyring,
+  File,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultStatus {
+  backend: VaultBackend,
+  /// Whether the encrypted file exists, i.e. a master password was set.
+  initialized: bool,
+  unlocked: bool,
+  /// Why the encrypted file could not be read at startup. Unlocking is
+  /// refused meanwhile so a new vault does not replace it.
+  load_error: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Sealed {
+  nonce: String,
+  data: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct VaultFile {
+  version: u32,
+  salt: String,
+  check: Sealed,
+  secrets: HashMap<String, Sealed>,
+}
+
+#[derive(Default)]
+struct VaultInner {
+  backend: Option<VaultBackend>,
+  file: Option<VaultFile>,
+  key: Option<[u8; 32]>,
+  load_error: Option<String>,
+}
+
+/// Passwords stored outside the webview. Callers keep only a reference such
+/// as `keyring:<id>` or `vault:<id>`; the prefix records where the secret
+/// lives, so references stay valid if the keyring later becomes available.
+#[derive(Default)]
+pub struct Vault {
+  inner: Mutex<VaultInner>,
+  path: Mutex<Option<PathBuf>>,
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N], AppError> {
+  let mut bytes = [0u8; N];
+  getrandom::fill(&mut bytes).map_err(map_err)?;
+  Ok(bytes)
+}
+
+fn derive_key(master_password: &str, salt: &[u8]) -> Result<[u8; 32], AppError> {
+  let mut key = [0u8; 32];
+  Argon2::default()
+    .hash_password_into(master_password.as_bytes(), salt, &mut key)
+    .map_err(map_err)?;
+  Ok(key)
+}
+
+fn seal(key: &[u8; 32], plaintext: &[u8]) -> Result<Sealed, AppError> {
+  let cipher = Aes256Gcm::new_from_slice(key).map_err(map_err)?;
+  let nonce = random_bytes::<12>()?;
+  let data = cipher
+    .encrypt(Nonce::from_slice(&nonce), plaintext)
+    .map_err(map_err)?;
+  Ok(Sealed {
+    nonce: BASE64_ENGINE.encode(nonce),
+    data: BASE64_ENGINE.encode(data),
+  })
+}
+
+fn open(key: &[u8; 32], sealed: &Sealed) -> Result<Vec<u8>, AppError> {
+  let cipher = Aes256Gcm::new_from_slice(key).map_err(map_err)?;
+  let nonce = BASE64_ENGINE.decode(&sealed.nonce).map_err(map_err)?;
+  let data = BASE64_ENGINE.decode(&sealed.data).map_err(map_err)?;
+  if nonce.len() != 12 {
+    return Err("Corrupt vault entry".into());
+  }
+  cipher
+    .decrypt(Nonce::from_slice(&nonce), data.as_ref())
+    .map_err(|_| "Unable to decrypt vault entry".into())
+}
+
+fn read_vault_file(raw: &str) -> Result<VaultFile, AppError> {
+  let file: VaultFile = serde_json::from_str(raw).map_err(map_err)?;
+  if file.version > VAULT_VERSION {
+    return Err(format!("Unsupported vault version {}", file.version).into());
+  }
+  Ok(file)
+}
+
+/// The keyring counts as usable if a lookup either succeeds or cleanly
+/// reports a missing entry. No Secret Service on Linux shows up as a
+/// platform or storage-access failure.
+fn probe_keyring() -> VaultBackend {
+  let usable = keyring::Entry::new(KEYRING_SERVICE, "probe")
+    .map(|entry| matches!(entry.get_password(), Ok(_) | Err(keyring::Error::NoEntry)))
+    .unwrap_or(false);
+  if usable {
+    VaultBackend::Keyring
+  } else {
+    VaultBackend::File
+  }
+}
+
+fn keyring_entry(id: &str) -> Result<keyring::Entry, AppError> {
+  keyring::Entry::new(KEYRING_SERVICE, id).map_err(map_err)
+}
+
+impl VaultInner {
+  fn backend(&mut self) -> VaultBackend {
+    *self.backend.get_or_insert_with(probe_keyring)
+  }
+
+  fn unlocked_file(&mut self) -> Result<(&mut VaultFile, [u8; 32]), AppError> {
+    match (self.file.as_mut(), self.key) {
+      (Some(file), Some(key)) => Ok((file, key)),
+      (None, _) => Err("Vault has no master password yet".into()),
+      (Some(_), None) => Err("Vault is locked".into()),
+    }
+  }
+}
+
+impl Vault {
+  pub fn load(&self, dir: PathBuf) -> Result<(), AppError> {
+    let path = dir.join(VAULT_FILE);
+    if let Ok(raw) = fs::read_to_string(&path) {
+      let file = match read_vault_file(&raw) {
+        Ok(file) => file,
+        Err(err) => {
+          self.lock()?.load_error = Some(err.message.clone());
+          return Err(err);
+        }
+      };
+      self.lock()?.file = Some(file);
+    }
+    *self.path.lock().map_err(map_err)? = Some(path);
+    Ok(())
+  }
+
+  fn lock(&self) -> Result<MutexGuard<'_, VaultInner>, AppError> {
+    self.inner.lock().map_err(map_err)
+  }
+
+  fn save(&self, file: &VaultFile) -> Result<(), AppError> {
+    let path = match self.path.lock().map_err(map_err)?.clone() {
+      Some(path) => path,
+      None => return Err("Vault storage is unavailable".into()),
+    };
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent).map_err(map_err)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(file).map_err(map_err)?).map_err(map_err)?;
+    fs::rename(&tmp, &path).map_err(map_err)
+  }
+
+  pub fn status(&self) -> Result<VaultStatus, AppError> {
+    let mut inner = self.lock()?;
+    Ok(VaultStatus {
+      backend: inner.backend(),
+      initialized: inner.file.is_some(),
+      unlocked: inner.key.is_some(),
+      load_error: inner.load_error.clone(),
+    })
+  }
+
+  /// Unlocks the encrypted file, creating it with `master_password` the first
+  /// time.
+  pub fn unlock(&self, master_password: &str) -> Result<(), AppError> {
+    if master_password.is_empty() {
+      return Err("Master password is required".into());
+    }
+    let mut inner = self.lock()?;
+    if let Some(error) = &inner.load_error {
+      return Err(format!("The vault file could not be read: {}", error).into());
+    }
+    match inner.file.as_ref() {
+      Some(file) => {
+        let salt = BASE64_ENGINE.decode(&file.salt).map_err(map_err)?;
+        let key = derive_key(master_password, &salt)?;
+        match open(&key, &file.check) {
+          Ok(check) if check == CHECK_VALUE => inner.key = Some(key),
+          _ => return Err(AppError::new(ErrorKind::AuthFailed, "Wrong master password")),
+        }
+      }
+      None => {
+        let salt = random_bytes::<16>()?;
+        let key = derive_key(master_password, &salt)?;
+        let file = VaultFile {
+          version: VAULT_VERSION,
+          salt: BASE64_ENGINE.encode(salt),
+          check: seal(&key, CHECK_VALUE)?,
+          secrets: HashMap::new(),
+        };
+        self.save(&file)?;
+        inner.file = Some(file);
+        inner.key = Some(key);
+      }
+    }
+    Ok(())
+  }
+
+  pub fn lock_vault(&self) -> Result<(), AppError> {
+    let mut inner = self.lock()?;
+    if let Some(key) = inner.key.as_mut() {
+      key.fill(0);
+    }
+    inner.key = None;
+    Ok(())
+  }
+
+  /// Stores `secret` and returns its reference. Passing an existing
+  /// reference replaces that secret in place.
+  pub fn store(&self, secret: &str, reference: Option<&str>) -> Result<String, AppError> {
+    let mut inner = self.lock()?;
+    let reference = match reference {
+      Some(reference) => reference.to_string(),
+      None => {
+        let id: String = random_bytes::<12>()?
+          .iter()
+          .map(|byte| format!("{:02x}", byte))
+          .collect();
+        match inner.backend() {
+          VaultBackend::Keyring => format!("{}{}", KEYRING_PREFIX, id),
+          VaultBackend::File => format!("{}{}", FILE_PREFIX, id),
+        }
+      }
+    };
+    if let Some(id) = reference.strip_prefix(KEYRING_PREFIX) {
+      keyring_entry(id)?.set_password(secret).map_err(map_err)?;
+    } else if let Some(id) = reference.strip_prefix(FILE_PREFIX) {
+      let (file, key) = inner.unlocked_file()?;
+      file.secrets.insert(id.to_string(), seal(&key, secret.as_bytes())?);
+      self.save(file)?;
+    } else {
+      return Err(format!("Invalid secret reference: {}", reference).into());
+    }
+    Ok(reference)
+  }
+
+  pub fn get(&self, reference: &str) -> Result<String, AppError> {
+    if let Some(id) = reference.strip_prefix(KEYRING_PREFIX) {
+      return keyring_entry(id)?.get_password().map_err(|err| match err {
+        keyring::Error::NoEntry => AppError::new(ErrorKind::NotFound, "Saved password not found"),
+        err => map_err(err),
+      });
+    }
+    let id = reference
+      .strip_prefix(FILE_PREFIX)
+      .ok_or_else(|| format!("Invalid secret reference: {}", reference))?;
+    let mut inner = self.lock()?;
+    let (file, key) = inner.unlocked_file()?;
+    let sealed = file
+      .secrets
+      .get(id)
+      .ok_or_else(|| "Saved password not found".to_string())?;
+    String::from_utf8(open(&key, sealed)?).map_err(map_err)
+  }
+
+  pub fn delete(&self, reference: &str) -> Result<(), AppError> {
+    if let Some(id) = reference.strip_prefix(KEYRING_PREFIX) {
+      return match keyring_entry(id)?.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(err) => Err(map_err(err)),
+      };
+    }
+    let id = reference
+      .strip_prefix(FILE_PREFIX)
+      .ok_or_else(|| format!("Invalid secret reference: {}", reference))?;
+    let mut inner = self.lock()?;
+    let (file, _) = inner.unlocked_file()?;
+    if file.secrets.remove(id).is_some() {
+      self.save(file)?;
+    }
+    Ok(())
+  }
+}
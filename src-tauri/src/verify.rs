@@ -0,0 +1,118 @@
+use md5::Md5;
+use serde::Serialize;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::error::{AppError, ErrorKind};
+use crate::map_err;
+use crate::remote::RemoteFs;
+
+/// How a finished transfer was checked, strongest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyMethod {
+  Sha256,
+  Sha1,
+  Md5,
+  Crc32,
+  /// The server offered no checksum, so only the sizes were compared.
+  Size,
+}
+
+impl VerifyMethod {
+  fn label(self) -> &'static str {
+    match self {
+      VerifyMethod::Sha256 => "SHA-256",
+      VerifyMethod::Sha1 => "SHA-1",
+      VerifyMethod::Md5 => "MD5",
+      VerifyMethod::Crc32 => "CRC32",
+      VerifyMethod::Size => "size",
+    }
+  }
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn read_chunks(path: &Path, mut update: impl FnMut(&[u8])) -> Result<(), AppError> {
+  let mut file = File::open(path).map_err(map_err)?;
+  let mut buffer = vec![0u8; 128 * 1024];
+  loop {
+    let read = file.read(&mut buffer).map_err(map_err)?;
+    if read == 0 {
+      return Ok(());
+    }
+    update(&buffer[..read]);
+  }
+}
+
+fn digest<D: Digest>(path: &Path) -> Result<String, AppError> {
+  let mut hasher = D::new();
+  read_chunks(path, |chunk| hasher.update(chunk))?;
+  Ok(hex(&hasher.finalize()))
+}
+
+/// Lower-case hex digest of a local file, in the form servers reply with.
+pub fn local_checksum(path: &Path, method: VerifyMethod) -> Result<String, AppError> {
+  match method {
+    VerifyMethod::Sha256 => digest::<Sha256>(path),
+    VerifyMethod::Sha1 => digest::<Sha1>(path),
+    VerifyMethod::Md5 => digest::<Md5>(path),
+    VerifyMethod::Crc32 => {
+      let mut hasher = crc32fast::Hasher::new();
+      read_chunks(path, |chunk| hasher.update(chunk))?;
+      Ok(format!("{:08x}", hasher.finalize()))
+    }
+    VerifyMethod::Size => Err("Size is not a checksum".into()),
+  }
+}
+
+pub fn checksums_match(method: VerifyMethod, local: &str, remote: &str) -> bool {
+  // CRC replies may drop leading zeros.
+  if method == VerifyMethod::Crc32 {
+    u32::from_str_radix(remote, 16).ok() == u32::from_str_radix(local, 16).ok()
+  } else {
+    remote.eq_ignore_ascii_case(local)
+  }
+}
+
+fn mismatch(path: &str, method: VerifyMethod, local: &str, remote: &str) -> AppError {
+  AppError::new(
+    ErrorKind::VerifyMismatch,
+    format!("{} of {} differs (local {}, server {})", method.label(), path, local, remote),
+  )
+}
+
+/// Compares a transferred file on both sides with the strongest checksum the
+/// server offers, falling back to the sizes. Returns the method used, or
+/// `None` when the server reports neither.
+pub fn verify_transfer(
+  remote: &mut dyn RemoteFs,
+  local_path: &str,
+  remote_path: &str,
+) -> Result<Option<VerifyMethod>, AppError> {
+  // A server that advertises a checksum but fails to compute it is treated
+  // like one without.
+  if let Ok(Some((method, remote_sum))) = remote.checksum(remote_path) {
+    let local_sum = local_checksum(Path::new(local_path), method)?;
+    if !checksums_match(method, &local_sum, &remote_sum) {
+      return Err(mismatch(remote_path, method, &local_sum, &remote_sum));
+    }
+    return Ok(Some(method));
+  }
+  let local_size = std::fs::metadata(local_path).map_err(map_err)?.len();
+  match remote.stat(remote_path).ok().and_then(|stat| stat.size) {
+    Some(size) if size == local_size => Ok(Some(VerifyMethod::Size)),
+    Some(size) => Err(mismatch(
+      remote_path,
+      VerifyMethod::Size,
+      &local_size.to_string(),
+      &size.to_string(),
+    )),
+    None => Ok(None),
+  }
+}
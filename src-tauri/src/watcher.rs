@@ -0,0 +1,277 @@
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::queue::{EnqueueRequest, TransferDirection};
+use crate::settings::ConflictPolicy;
+use crate::{join_remote, log_event, map_err, AppState};
+
+/// How long a file must go without events, and keep the same size, before it
+/// is uploaded. Covers cameras and card readers that write in bursts.
+const SETTLE_TIME: Duration = Duration::from_millis(1500);
+const TICK: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Debug, Serialize)]
+pub struct WatchInfo {
+  id: String,
+  local_dir: String,
+  remote_dir: String,
+  session_key: String,
+}
+
+struct ActiveWatch {
+  info: WatchInfo,
+  // Dropping the watcher ends its event stream.
+  _watcher: RecommendedWatcher,
+  stop: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+pub struct WatchRegistry {
+  watches: Mutex<HashMap<String, ActiveWatch>>,
+  next_id: AtomicU64,
+}
+
+/// Files the app or common editors write while a file is incomplete.
+fn is_ignored(path: &Path) -> bool {
+  let name = match path.file_name() {
+    Some(name) => name.to_string_lossy().to_lowercase(),
+    None => return true,
+  };
+  name.ends_with(".part")
+    || name.ends_with(".tmp")
+    || name.ends_with(".temp")
+    || name.ends_with(".swp")
+    || name.ends_with(".crdownload")
+    || name.ends_with('~')
+    || name.starts_with("~$")
+    || name.starts_with(".~")
+    || name == ".ds_store"
+    || name == "thumbs.db"
+}
+
+struct Pending {
+  last_event: Instant,
+  size: Option<u64>,
+}
+
+/// Collects file events and hands settled files to the transfer queue. Runs
+/// until `stop` is set or the watcher is dropped.
+fn run_debouncer(app: AppHandle, info: WatchInfo, events: Receiver<PathBuf>, stop: Arc<AtomicBool>) {
+  let root = PathBuf::from(&info.local_dir);
+  let mut pending: HashMap<PathBuf, Pending> = HashMap::new();
+  // Settled while the session was disconnected, uploaded once it is back.
+  let mut waiting: Vec<PathBuf> = Vec::new();
+  let mut created_dirs: HashSet<String> = HashSet::new();
+  let mut last_sweep = Instant::now();
+  while !stop.load(Ordering::Relaxed) {
+    match events.recv_timeout(TICK) {
+      Ok(path) => {
+        waiting.retain(|waiting| waiting != &path);
+        let entry = pending.entry(path).or_insert(Pending {
+          last_event: Instant::now(),
+          size: None,
+        });
+        entry.last_event = Instant::now();
+      }
+      Err(RecvTimeoutError::Disconnected) => return,
+      Err(RecvTimeoutError::Timeout) => {}
+    }
+    // Swept on a deadline, since a busy folder may never go a whole tick
+    // without events.
+    if last_sweep.elapsed() < TICK {
+      continue;
+    }
+    last_sweep = Instant::now();
+
+    let mut ready = std::mem::take(&mut waiting);
+    let held = ready.len();
+    pending.retain(|path, entry| {
+      if entry.last_event.elapsed() < SETTLE_TIME {
+        return true;
+      }
+      let size = match std::fs::metadata(path) {
+        Ok(meta) if meta.is_file() => meta.len(),
+        _ => return false,
+      };
+      if entry.size != Some(size) {
+        entry.size = Some(size);
+        entry.last_event = Instant::now();
+        return true;
+      }
+      ready.push(path.clone());
+      false
+    });
+    if ready.is_empty() {
+      continue;
+    }
+    if let Err(unsent) = enqueue_uploads(&app, &info, &root, ready, &mut created_dirs) {
+      if unsent.len() > held {
+        if let Some(window) = app.get_window("main") {
+          log_event(
+            &window,
+            "error",
+            format!("Watch {}: not connected, holding {} file(s) until reconnected", info.local_dir, unsent.len()),
+          );
+        }
+      }
+      waiting = unsent;
+    }
+  }
+}
+
+/// Queues the uploads, or hands the paths back while the session is not
+/// connected.
+fn enqueue_uploads(
+  app: &AppHandle,
+  info: &WatchInfo,
+  root: &Path,
+  paths: Vec<PathBuf>,
+  created_dirs: &mut HashSet<String>,
+) -> Result<(), Vec<PathBuf>> {
+  let window = match app.get_window("main") {
+    Some(window) => window,
+    None => return Ok(()),
+  };
+  let state = app.state::<AppState>();
+  // Looked up by server rather than id, so the watch survives a reconnect.
+  let (session_id, session) = match state.sessions.find_by_key(&info.session_key) {
+    Some(found) => found,
+    None => return Err(paths),
+  };
+
+  let mut requests = Vec::new();
+  let mut needed_dirs = Vec::new();
+  for path in paths {
+    let relative = match path.strip_prefix(root) {
+      Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+      Err(_) => continue,
+    };
+    let remote_path = join_remote(&info.remote_dir, &relative);
+    if let Some((parent, _)) = relative.rsplit_once('/') {
+      let mut dir = info.remote_dir.clone();
+      for part in parent.split('/') {
+        dir = join_remote(&dir, part);
+        if !created_dirs.contains(&dir) && !needed_dirs.contains(&dir) {
+          needed_dirs.push(dir.clone());
+        }
+      }
+    }
+    // A changed file replaces its earlier upload.
+    requests.push(
+      EnqueueRequest::new(TransferDirection::Upload, path.to_string_lossy().to_string(), remote_path)
+        .with_conflict_policy(ConflictPolicy::Overwrite),
+    );
+  }
+
+  if !needed_dirs.is_empty() {
+    match session.checkout_worker(&window) {
+      Ok(mut worker) => {
+        for dir in needed_dirs {
+          // Errors are expected for folders that already exist, so only a
+          // folder that is really there counts as created.
+          let exists = worker.mkdir(&dir).is_ok()
+            || worker.stat(&dir).map(|stat| stat.is_dir).unwrap_or(false);
+          if exists {
+            created_dirs.insert(dir);
+          }
+        }
+        session.checkin_worker(worker);
+      }
+      Err(err) => log_event(&window, "error", format!("Watch {}: {}", info.local_dir, err)),
+    }
+  }
+
+  let count = requests.len();
+  match state.queue.enqueue(&session_id, &session, requests) {
+    Ok(_) => log_event(&window, "info", format!("Watch {}: queued {} upload(s)", info.local_dir, count)),
+    Err(err) => log_event(&window, "error", format!("Watch {}: {}", info.local_dir, err)),
+  }
+  Ok(())
+}
+
+impl WatchRegistry {
+  pub fn start(
+    &self,
+    app: AppHandle,
+    session_key: String,
+    local_dir: String,
+    remote_dir: String,
+  ) -> Result<WatchInfo, AppError> {
+    if !Path::new(&local_dir).is_dir() {
+      return Err("Path is not a directory".into());
+    }
+    let id = format!("watch-{}", self.next_id.fetch_add(1, Ordering::Relaxed) + 1);
+    let info = WatchInfo {
+      id: id.clone(),
+      local_dir,
+      remote_dir,
+      session_key,
+    };
+
+    let (sender, receiver) = channel();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+      let event = match result {
+        Ok(event) => event,
+        Err(_) => return,
+      };
+      if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+        return;
+      }
+      for path in event.paths {
+        if !is_ignored(&path) {
+          let _ = sender.send(path);
+        }
+      }
+    })
+    .map_err(map_err)?;
+    watcher
+      .watch(Path::new(&info.local_dir), RecursiveMode::Recursive)
+      .map_err(map_err)?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_info = info.clone();
+    let thread_stop = stop.clone();
+    std::thread::spawn(move || run_debouncer(app, thread_info, receiver, thread_stop));
+
+    self.watches.lock().map_err(map_err)?.insert(
+      id,
+      ActiveWatch {
+        info: info.clone(),
+        _watcher: watcher,
+        stop,
+      },
+    );
+    Ok(info)
+  }
+
+  pub fn stop(&self, id: &str) -> Result<(), AppError> {
+    let watch = self
+      .watches
+      .lock()
+      .map_err(map_err)?
+      .remove(id)
+      .ok_or_else(|| format!("Unknown watch: {}", id))?;
+    watch.stop.store(true, Ordering::Relaxed);
+    Ok(())
+  }
+
+  pub fn list(&self) -> Result<Vec<WatchInfo>, AppError> {
+    Ok(
+      self
+        .watches
+        .lock()
+        .map_err(map_err)?
+        .values()
+        .map(|watch| watch.info.clone())
+        .collect(),
+    )
+  }
+}
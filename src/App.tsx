@@ -36,6 +36,7 @@ import {
   normalizeLocalInput,
   parseDragPayload,
   toLocalFileId,
+  errorMessage,
 } from "./utils";
 import { IconChevronDown } from "./icons";
 import { dirname, join } from "@tauri-apps/api/path";
@@ -156,8 +157,9 @@ export default function App() {
 
   const {
     host, setHost, port, setPort, protocol, setProtocol,
-    sftpPort, setSftpPort, username, setUsername, password, setPassword,
-    connected, connecting,
+    sftpPort, setSftpPort, security, setSecurity,
+    username, setUsername, password, setPassword,
+    connected, connecting, sessionId,
     connectionDetailOpen, setConnectionDetailOpen,
     ftpBookmarks, selectedFtpBookmark, savePassword, setSavePassword,
     connectionConfig,
@@ -413,7 +415,7 @@ export default function App() {
           const target = s.selectedLocal[0] ?? s.localPath;
           if (target && target !== "this_pc") {
             invoke("open_properties", { path: target }).catch((error) => {
-              const message = error instanceof Error ? error.message : String(error);
+              const message = errorMessage(error);
               s.addLog("error", message);
             });
           }
@@ -523,7 +525,7 @@ export default function App() {
         return;
       }
       invoke("launch_path", { path: entry.path }).catch((error) => {
-        const message = error instanceof Error ? error.message : String(error);
+        const message = errorMessage(error);
         addLog("error", message);
       });
       return;
@@ -578,7 +580,7 @@ export default function App() {
         return;
       }
       invoke("open_properties", { path: entry.path }).catch((error) => {
-        const message = error instanceof Error ? error.message : String(error);
+        const message = errorMessage(error);
         addLog("error", message);
       });
       return;
@@ -705,7 +707,7 @@ export default function App() {
           if (scope === "remote") {
             if (mode === "cut") {
               if (isTauri) {
-                await invoke("rename_path", { from: entry.path, to: target });
+                await invoke("rename_path", { sessionId, from: entry.path, to: target });
               } else {
                 await ftpRequest("rename", {
                   host,
@@ -720,7 +722,7 @@ export default function App() {
               }
             } else {
               if (isTauri) {
-                await invoke("copy_remote", { from: entry.path, to: target, isDir: entry.isDir });
+                await invoke("copy_remote", { sessionId, from: entry.path, to: target, isDir: entry.isDir });
               } else {
                 if (entry.isDir) {
                   addLog("error", "Remote folder copy is not supported in the browser.");
@@ -756,7 +758,7 @@ export default function App() {
                 try {
                   blob = await sendDownload();
                 } catch (error) {
-                  const message = error instanceof Error ? error.message : String(error);
+                  const message = errorMessage(error);
                   if (!shouldFallbackToSftp(message)) throw error;
                   blob = await sendDownload("sftp");
                 }
@@ -794,7 +796,7 @@ export default function App() {
                 for (const entry of entries) {
                   const target = buildRemotePath(remotePath || "/", entry.name);
                   if (isTauri) {
-                    await invoke("rename_path", { from: target, to: entry.path });
+                    await invoke("rename_path", { sessionId, from: target, to: entry.path });
                   } else {
                     await ftpRequest("rename", {
                       host,
@@ -820,7 +822,7 @@ export default function App() {
                 const target = buildRemotePath(remotePath || "/", entry.name);
                 if (mode === "cut") {
                   if (isTauri) {
-                    await invoke("rename_path", { from: entry.path, to: target });
+                    await invoke("rename_path", { sessionId, from: entry.path, to: target });
                   } else {
                     await ftpRequest("rename", {
                       host,
@@ -835,7 +837,7 @@ export default function App() {
                   }
                 } else {
                   if (isTauri) {
-                    await invoke("copy_remote", { from: entry.path, to: target, isDir: entry.isDir });
+                    await invoke("copy_remote", { sessionId, from: entry.path, to: target, isDir: entry.isDir });
                   } else {
                     if (entry.isDir) {
                       addLog("error", "Remote folder copy redo not supported in browser.");
@@ -854,7 +856,7 @@ export default function App() {
         setClipboardState(null);
       }
     } catch (error) {
-      const message = error instanceof Error ? error.message : String(error);
+      const message = errorMessage(error);
       addLog("error", message);
     }
   };
@@ -883,7 +885,7 @@ export default function App() {
       return;
     }
     invoke("open_with_dialog", { path: contextEntry.path }).catch((error) => {
-      const message = error instanceof Error ? error.message : String(error);
+      const message = errorMessage(error);
       addLog("error", message);
     });
   };
@@ -932,11 +934,11 @@ export default function App() {
   // ── Orchestration wrappers (cross-hook coordination) ──
   const handleConnect = async () => {
     try {
-      const cwd = await connect();
+      const { cwd, sessionId: nextSessionId } = await connect();
       addLog("success", "Connected.");
-      await refreshRemote(cwd, true);
+      await refreshRemote(cwd, true, nextSessionId);
     } catch (error) {
-      const message = error instanceof Error ? error.message : String(error);
+      const message = errorMessage(error);
       addLog("error", message);
     }
   };
@@ -1100,7 +1102,7 @@ export default function App() {
         } else {
           const target = buildRemotePath(remotePath || "/", modal.targetName);
           if (isTauri) {
-            await invoke("delete_path", { path: target, isDir: modal.isDir });
+            await invoke("delete_path", { sessionId, path: target, isDir: modal.isDir });
           } else {
             await ftpRequest("delete", {
               host,
@@ -1128,12 +1130,12 @@ export default function App() {
           addLog("error", "Bookmark name required.");
           return;
         }
-        saveFtpBookmark(name);
+        await saveFtpBookmark(name);
       }
 
       closeModal();
     } catch (error) {
-      const message = error instanceof Error ? error.message : String(error);
+      const message = errorMessage(error);
       addLog("error", message);
     }
   };
@@ -1168,7 +1170,7 @@ export default function App() {
       return;
     }
     if (isTauri) {
-      await invoke("delete_path", { path, isDir });
+      await invoke("delete_path", { sessionId, path, isDir });
     } else {
       await ftpRequest("delete", {
         host,
@@ -1195,7 +1197,7 @@ export default function App() {
       return;
     }
     if (isTauri) {
-      await invoke("create_dir", { path });
+      await invoke("create_dir", { sessionId, path });
     } else {
       await ftpRequest("mkdir", {
         host,
@@ -1221,7 +1223,7 @@ export default function App() {
       return;
     }
     if (isTauri) {
-      await invoke("create_remote_file", { path });
+      await invoke("create_remote_file", { sessionId, path });
     } else {
       const blob = new Blob([""], { type: "text/plain" });
       const form = new FormData();
@@ -1306,7 +1308,7 @@ export default function App() {
         const from = buildRemotePath(remotePath || "/", renameState.name);
         const to = buildRemotePath(remotePath || "/", nextName);
         if (isTauri) {
-          await invoke("rename_path", { from, to });
+          await invoke("rename_path", { sessionId, from, to });
         } else {
           await ftpRequest("rename", {
             host,
@@ -1345,7 +1347,7 @@ export default function App() {
             const from = buildRemotePath(remotePath || "/", nextName);
             const to = buildRemotePath(remotePath || "/", fromName);
             if (isTauri) {
-              await invoke("rename_path", { from, to });
+              await invoke("rename_path", { sessionId, from, to });
             } else {
               await ftpRequest("rename", {
                 host,
@@ -1379,7 +1381,7 @@ export default function App() {
             const from = buildRemotePath(remotePath || "/", fromName);
             const to = buildRemotePath(remotePath || "/", nextName);
             if (isTauri) {
-              await invoke("rename_path", { from, to });
+              await invoke("rename_path", { sessionId, from, to });
             } else {
               await ftpRequest("rename", {
                 host,
@@ -1398,7 +1400,7 @@ export default function App() {
       });
       cancelInlineRename();
     } catch (error) {
-      const message = error instanceof Error ? error.message : String(error);
+      const message = errorMessage(error);
       addLog("error", message);
     }
   };
@@ -1456,7 +1458,7 @@ export default function App() {
         });
       }
     } catch (error) {
-      const message = error instanceof Error ? error.message : String(error);
+      const message = errorMessage(error);
       addLog("error", message);
     }
   };
@@ -1506,7 +1508,7 @@ export default function App() {
         });
       }
     } catch (error) {
-      const message = error instanceof Error ? error.message : String(error);
+      const message = errorMessage(error);
       addLog("error", message);
     }
   };
@@ -1976,6 +1978,8 @@ export default function App() {
         setProtocol={setProtocol}
         sftpPort={sftpPort}
         setSftpPort={setSftpPort}
+        security={security}
+        setSecurity={setSecurity}
         username={username}
         setUsername={setUsername}
         password={password}
@@ -1994,7 +1998,6 @@ export default function App() {
         onBookmarkSelect={handleFtpBookmarkSelect}
         onDeleteBookmark={deleteFtpBookmark}
         onSaveBookmark={openFtpBookmarkModal}
-        addLog={addLog}
       />
 
       <main className="shell" ref={shellRef}>
@@ -2236,7 +2239,7 @@ export default function App() {
               return;
             }
             invoke("open_properties", { path: localPath }).catch((error) => {
-              const message = error instanceof Error ? error.message : String(error);
+              const message = errorMessage(error);
               addLog("error", message);
             });
           }}
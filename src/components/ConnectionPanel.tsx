@@ -1,5 +1,5 @@
 import { Button, Dropdown, Input, Panel, Toggle } from "@enderfall/ui";
-import type { FtpBookmark } from "../types";
+import type { FtpBookmark, FtpSecurity } from "../types";
 import { isTauri } from "../constants";
 import { IconLock, IconUnlock, IconChevronDown } from "../icons";
 
@@ -12,6 +12,8 @@ interface ConnectionPanelProps {
   setProtocol: (value: "ftp" | "sftp") => void;
   sftpPort: number;
   setSftpPort: (value: number) => void;
+  security: FtpSecurity;
+  setSecurity: (value: FtpSecurity) => void;
   username: string;
   setUsername: (value: string) => void;
   password: string;
@@ -30,7 +32,6 @@ interface ConnectionPanelProps {
   onBookmarkSelect: (value: string) => void;
   onDeleteBookmark: (name: string) => void;
   onSaveBookmark: () => void;
-  addLog: (level: string, message: string) => void;
 }
 
 const ConnectionPanel = ({
@@ -42,6 +43,8 @@ const ConnectionPanel = ({
   setProtocol,
   sftpPort,
   setSftpPort,
+  security,
+  setSecurity,
   username,
   setUsername,
   password,
@@ -60,7 +63,6 @@ const ConnectionPanel = ({
   onBookmarkSelect,
   onDeleteBookmark,
   onSaveBookmark,
-  addLog,
 }: ConnectionPanelProps) => {
   const selectedFtp = ftpBookmarks.find((item) => item.name === selectedFtpBookmark) ?? null;
 
@@ -103,28 +105,18 @@ const ConnectionPanel = ({
                 variant="bookmark"
                 layout="field"
                 value={protocol}
-                onChange={(value: any) => {
-                  const next = value as "ftp" | "sftp";
-                  if (isTauri && next === "sftp") {
-                    addLog("error", "SFTP is available in the web app only.");
-                    return;
-                  }
-                  setProtocol(next);
-                }}
+                onChange={(value: any) => setProtocol(value as "ftp" | "sftp")}
                 sections={[
                   {
                     options: [
                       { value: "ftp", label: "FTP" },
-                      {
-                        value: "sftp",
-                        label: isTauri ? "SFTP (web only)" : "SFTP",
-                      },
+                      { value: "sftp", label: "SFTP" },
                     ],
                   },
                 ]}
               />
             </label>
-            {protocol === "sftp" && !isTauri ? (
+            {protocol === "sftp" ? (
               <label>
                 SFTP Port
                 <Input
@@ -134,6 +126,31 @@ const ConnectionPanel = ({
                 />
               </label>
             ) : null}
+            {protocol === "ftp" && isTauri ? (
+              <label>
+                Security
+                <Dropdown
+                  variant="bookmark"
+                  layout="field"
+                  value={security}
+                  onChange={(value: any) => {
+                    const next = value as FtpSecurity;
+                    setSecurity(next);
+                    if (next === "implicit" && port === 21) setPort(990);
+                    if (next !== "implicit" && port === 990) setPort(21);
+                  }}
+                  sections={[
+                    {
+                      options: [
+                        { value: "none", label: "None" },
+                        { value: "explicit", label: "Explicit TLS" },
+                        { value: "implicit", label: "Implicit TLS" },
+                      ],
+                    },
+                  ]}
+                />
+              </label>
+            ) : null}
             <label>
               Username
               <Input
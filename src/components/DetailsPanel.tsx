@@ -1,7 +1,16 @@
 import { Panel } from "@enderfall/ui";
 import { invoke } from "@tauri-apps/api/tauri";
 import { isTauri, maxVideoPreviewBytes } from "../constants";
-import { formatBytes, formatDate, isImageFile, isVideoFile, toImageKey, toVideoKey } from "../utils";
+import type { ErrorKind } from "../types";
+import {
+  errorKind,
+  formatBytes,
+  formatDate,
+  isImageFile,
+  isVideoFile,
+  toImageKey,
+  toVideoKey,
+} from "../utils";
 
 interface DetailsItem {
   scope: string;
@@ -23,9 +32,9 @@ interface DetailsPanelProps {
   detailsRef: React.RefObject<HTMLDivElement | null>;
   imageCache: Record<string, string>;
   videoPreviewCache: Record<string, string>;
-  videoPreviewErrors: Record<string, string>;
+  videoPreviewErrors: Record<string, ErrorKind>;
   setVideoPreviewCache: React.Dispatch<React.SetStateAction<Record<string, string>>>;
-  setVideoPreviewErrors: React.Dispatch<React.SetStateAction<Record<string, string>>>;
+  setVideoPreviewErrors: React.Dispatch<React.SetStateAction<Record<string, ErrorKind>>>;
 }
 
 const DetailsPanel = ({
@@ -72,11 +81,9 @@ const DetailsPanel = ({
                       }));
                     })
                     .catch((error) => {
-                      const message =
-                        error instanceof Error ? error.message : String(error);
                       setVideoPreviewErrors((prev) => ({
                         ...prev,
-                        [key]: message,
+                        [key]: errorKind(error) ?? "other",
                       }));
                     });
                 }}
@@ -69,8 +69,8 @@ const TransferPanel = ({
                 Clear done
               </button>
             )}
-            {queue.some((i) => i.status === "queued") && (
-              <button type="button" className="queue-action-btn" title="Cancel queued" onClick={onCancelQueued}>
+            {queue.some((i) => i.status === "queued" || (isTauri && i.status === "active")) && (
+              <button type="button" className="queue-action-btn" title="Cancel queued and running" onClick={onCancelQueued}>
                 Cancel
               </button>
             )}
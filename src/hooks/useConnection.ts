@@ -1,8 +1,9 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/tauri";
-import type { ConnectResponse, FtpBookmark } from "../types";
+import { once } from "@tauri-apps/api/event";
+import type { CertificatePrompt, ConnectResponse, FtpBookmark, FtpSecurity } from "../types";
 import { isTauri } from "../constants";
-import { ftpRequest, loadFtpBookmarks, saveFtpBookmarks } from "../utils";
+import { ftpRequest, loadFtpBookmarks, saveFtpBookmarks, errorMessage } from "../utils";
 
 interface UseConnectionParams {
   addLog: (level: string, message: string) => void;
@@ -13,9 +14,11 @@ export function useConnection({ addLog }: UseConnectionParams) {
   const [port, setPort] = useState(21);
   const [protocol, setProtocol] = useState<"ftp" | "sftp">("ftp");
   const [sftpPort, setSftpPort] = useState(22);
+  const [security, setSecurity] = useState<FtpSecurity>("none");
   const [username, setUsername] = useState("");
   const [password, setPassword] = useState("");
   const [connected, setConnected] = useState(false);
+  const [sessionId, setSessionId] = useState<string | null>(null);
   const [connectionDetailOpen, setConnectionDetailOpen] = useState(true);
   const [connecting, setConnecting] = useState(false);
   const [ftpBookmarks, setFtpBookmarks] = useState<FtpBookmark[]>(loadFtpBookmarks());
@@ -26,25 +29,91 @@ export function useConnection({ addLog }: UseConnectionParams) {
     setConnectionDetailOpen(!connected);
   }, [connected]);
 
-  /** Attempts connection. Returns the remote cwd on success, throws on failure. */
-  const connect = async (): Promise<string> => {
-    if (isTauri && protocol === "sftp") {
-      throw new Error("SFTP is available in the web app only.");
+  // Bookmarks from older versions kept the password in plaintext. Move it to
+  // the vault, or drop it where there is none or the vault is locked.
+  useEffect(() => {
+    const legacy = ftpBookmarks.filter((item) => item.password);
+    if (!legacy.length) return;
+    const migrate = async () => {
+      const next: FtpBookmark[] = [];
+      for (const item of ftpBookmarks) {
+        const { password: secret, ...rest } = item;
+        if (!secret) {
+          next.push(rest);
+          continue;
+        }
+        let reference: string | null = null;
+        if (isTauri) {
+          try {
+            reference = await invoke<string>("vault_store", { secret, reference: null });
+          } catch (error) {
+            addLog("error", `Saved password for ${item.name} removed, enter it again: ${errorMessage(error)}`);
+          }
+        }
+        next.push({ ...rest, passwordRef: reference });
+      }
+      setFtpBookmarks(next);
+      saveFtpBookmarks(next);
+    };
+    migrate().catch((error) => addLog("error", errorMessage(error)));
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, []);
+
+  /** The vault reference of the selected bookmark, while the form still shows its server and user. */
+  const savedPasswordRef = () => {
+    const selected = ftpBookmarks.find((item) => item.name === selectedFtpBookmark);
+    if (!selected || selected.host !== host || selected.username !== username) return null;
+    return selected.passwordRef ?? null;
+  };
+
+  /** Connects through Tauri, asking the user to accept an untrusted certificate or host key once. */
+  const connectNative = async (): Promise<ConnectResponse> => {
+    const attempt = (fingerprint: string | null) =>
+      invoke<ConnectResponse>("connect", {
+        config: {
+          host,
+          port: protocol === "sftp" ? sftpPort : port,
+          protocol,
+          username, password, security,
+          password_ref: savedPasswordRef(),
+          certificate: { mode: "accept_once", fingerprint },
+        },
+      });
+    let prompt: CertificatePrompt | null = null;
+    const unlisten = await once<CertificatePrompt>("tls-certificate-prompt", (event) => {
+      prompt = event.payload;
+    });
+    try {
+      return await attempt(null);
+    } catch (error) {
+      const pending = prompt as CertificatePrompt | null;
+      if (!pending) throw error;
+      const accepted = window.confirm(
+        `The ${protocol === "sftp" ? "host key" : "certificate"} for ${pending.host} is not trusted (${pending.reason}).\n\n` +
+        `SHA-256 fingerprint:\n${pending.fingerprint}\n\nConnect anyway?`,
+      );
+      if (!accepted) throw error;
+      return await attempt(pending.fingerprint);
+    } finally {
+      unlisten();
     }
+  };
+
+  /** Attempts connection. Returns the remote cwd and session on success, throws on failure. */
+  const connect = async (): Promise<{ cwd: string; sessionId: string | null }> => {
     if (!host) {
       throw new Error("Host is required.");
     }
     setConnecting(true);
     try {
       const response = isTauri
-        ? await invoke<ConnectResponse>("connect", {
-            config: { host, port, username, password },
-          })
+        ? await connectNative()
         : await ftpRequest<ConnectResponse>("connect", {
             host, port, username, password, protocol, sftpPort,
           });
+      setSessionId(response.session_id ?? null);
       setConnected(true);
-      return response.cwd || "/";
+      return { cwd: response.cwd || "/", sessionId: response.session_id ?? null };
     } finally {
       setConnecting(false);
     }
@@ -52,14 +121,15 @@ export function useConnection({ addLog }: UseConnectionParams) {
 
   /** Disconnects from the server. Clears connection state only — caller resets remote browser. */
   const disconnect = async () => {
-    if (isTauri) {
+    if (isTauri && sessionId) {
       try {
-        await invoke("disconnect");
+        await invoke("disconnect", { sessionId });
       } catch (error) {
-        const message = error instanceof Error ? error.message : String(error);
+        const message = errorMessage(error);
         addLog("error", message);
       }
     }
+    setSessionId(null);
     setConnected(false);
   };
 
@@ -70,12 +140,21 @@ export function useConnection({ addLog }: UseConnectionParams) {
     setHost(selected.host);
     setPort(selected.port);
     setUsername(selected.username);
-    setPassword(selected.password ?? "");
-    setSavePassword(Boolean(selected.password));
+    // A typed password takes precedence over the saved one on connect.
+    setPassword("");
+    setSavePassword(Boolean(selected.passwordRef));
+  };
+
+  /** Removes a saved password from the vault once no remaining bookmark uses it. */
+  const forgetPassword = (reference: string | null | undefined, remaining: FtpBookmark[]) => {
+    if (!reference || !isTauri) return;
+    if (remaining.some((item) => item.passwordRef === reference)) return;
+    invoke("vault_delete", { reference }).catch((error) => addLog("error", errorMessage(error)));
   };
 
   const deleteFtpBookmark = (name: string) => {
     const next = ftpBookmarks.filter((item) => item.name !== name);
+    forgetPassword(ftpBookmarks.find((item) => item.name === name)?.passwordRef, next);
     setFtpBookmarks(next);
     saveFtpBookmarks(next);
     if (selectedFtpBookmark === name) {
@@ -83,26 +162,40 @@ export function useConnection({ addLog }: UseConnectionParams) {
     }
   };
 
-  const saveFtpBookmark = (name: string) => {
-    const next = ftpBookmarks.filter((item) => item.name !== name);
-    next.unshift({
-      name,
-      host,
-      port,
-      username,
-      password: savePassword ? password : null,
-    });
+  /** Saves the current connection. The password goes to the vault, never to local storage. */
+  const saveFtpBookmark = async (name: string) => {
+    const others = ftpBookmarks.filter((item) => item.name !== name);
+    const existing = ftpBookmarks.find((item) => item.name === name)?.passwordRef ?? null;
+    // Another bookmark may share the secret; replace it in place only if not.
+    const owned = existing && !others.some((item) => item.passwordRef === existing) ? existing : null;
+    let reference: string | null = null;
+    if (savePassword && isTauri) {
+      if (password) {
+        try {
+          reference = await invoke<string>("vault_store", { secret: password, reference: owned });
+        } catch (error) {
+          addLog("error", `Password not saved: ${errorMessage(error)}`);
+        }
+      } else {
+        reference = savedPasswordRef();
+      }
+    }
+    const next = [{ name, host, port, username, passwordRef: reference }, ...others];
+    if (reference !== existing) {
+      forgetPassword(existing, next);
+    }
     setFtpBookmarks(next);
     saveFtpBookmarks(next);
     setSelectedFtpBookmark(name);
   };
 
-  const connectionConfig = { host, port, username, password, protocol, sftpPort };
+  const connectionConfig = { host, port, username, password, protocol, sftpPort, sessionId };
 
   return {
     host, setHost, port, setPort, protocol, setProtocol,
-    sftpPort, setSftpPort, username, setUsername, password, setPassword,
-    connected, setConnected, connecting,
+    sftpPort, setSftpPort, security, setSecurity,
+    username, setUsername, password, setPassword,
+    connected, setConnected, connecting, sessionId,
     connectionDetailOpen, setConnectionDetailOpen,
     ftpBookmarks, selectedFtpBookmark, savePassword, setSavePassword,
     connectionConfig,
@@ -1,5 +1,6 @@
 import { useState } from "react";
 import type { HistoryAction } from "../types";
+import { errorMessage } from "../utils";
 
 export function useHistory(addLog: (level: string, message: string) => void) {
   const [historyStack, setHistoryStack] = useState<HistoryAction[]>([]);
@@ -18,7 +19,7 @@ export function useHistory(addLog: (level: string, message: string) => void) {
       setHistoryStack((prev) => prev.slice(1));
       setRedoStack((prev) => [action, ...prev]);
     } catch (error) {
-      const message = error instanceof Error ? error.message : String(error);
+      const message = errorMessage(error);
       addLog("error", message);
     }
   };
@@ -31,7 +32,7 @@ export function useHistory(addLog: (level: string, message: string) => void) {
       setRedoStack((prev) => prev.slice(1));
       setHistoryStack((prev) => [action, ...prev]);
     } catch (error) {
-      const message = error instanceof Error ? error.message : String(error);
+      const message = errorMessage(error);
       addLog("error", message);
     }
   };
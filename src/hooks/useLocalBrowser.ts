@@ -9,7 +9,7 @@ import {
   pictureDir,
   join,
 } from "@tauri-apps/api/path";
-import type { Favorite, LocalEntry, LocalListResponse, SortBy, SortOrder, ViewMode } from "../types";
+import type { ErrorKind, Favorite, LocalEntry, LocalListResponse, SortBy, SortOrder, ViewMode } from "../types";
 import { isTauri, viewModeOptions, sortPrimaryOptions, sortMoreOptions } from "../constants";
 import {
   getExtension,
@@ -23,6 +23,7 @@ import {
   toTimestamp,
   toVideoKey,
   viewThumbSize,
+  errorMessage,
 } from "../utils";
 
 interface UseLocalBrowserParams {
@@ -63,7 +64,7 @@ export function useLocalBrowser({ addLog, isPremium }: UseLocalBrowserParams) {
   const [imageCache, setImageCache] = useState<Record<string, string>>({});
   const [videoThumbCache, setVideoThumbCache] = useState<Record<string, string>>({});
   const [videoPreviewCache, setVideoPreviewCache] = useState<Record<string, string>>({});
-  const [videoPreviewErrors, setVideoPreviewErrors] = useState<Record<string, string>>({});
+  const [videoPreviewErrors, setVideoPreviewErrors] = useState<Record<string, ErrorKind>>({});
   const blobUrlsRef = useRef<Set<string>>(new Set());
 
   // Sync address bar
@@ -146,7 +147,7 @@ export function useLocalBrowser({ addLog, isPremium }: UseLocalBrowserParams) {
       setLocalEntries(response.entries);
       setLocalPath(response.path || "this_pc");
     } catch (error) {
-      const message = error instanceof Error ? error.message : String(error);
+      const message = errorMessage(error);
       addLog("error", message);
     }
   };
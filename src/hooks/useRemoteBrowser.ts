@@ -2,7 +2,7 @@ import { useEffect, useMemo, useState } from "react";
 import { invoke } from "@tauri-apps/api/tauri";
 import type { FtpEntry, ListResponse, SortBy, SortOrder, ViewMode } from "../types";
 import { isTauri, viewModeOptions, sortPrimaryOptions, sortMoreOptions } from "../constants";
-import { ftpRequest, getExtension, sortEntries, toTimestamp } from "../utils";
+import { ftpRequest, getExtension, sortEntries, toTimestamp, errorMessage } from "../utils";
 import type { ConnectionConfig } from "./useConnection";
 
 interface UseRemoteBrowserParams {
@@ -49,19 +49,22 @@ export function useRemoteBrowser({ connectionConfig, connected, addLog }: UseRem
   useEffect(() => { localStorage.setItem("sortByRemote", remoteSortBy); }, [remoteSortBy]);
   useEffect(() => { localStorage.setItem("sortOrderRemote", remoteSortOrder); }, [remoteSortOrder]);
 
-  const refreshRemote = async (path?: string, force?: boolean) => {
+  const refreshRemote = async (path?: string, force?: boolean, session?: string | null) => {
     if (!connected && !force) return;
     try {
       const { host, port, username, password, protocol, sftpPort } = connectionConfig;
       const response = isTauri
-        ? await invoke<ListResponse>("list_dir", { path: path ?? null })
+        ? await invoke<ListResponse>("list_dir", {
+            sessionId: session ?? connectionConfig.sessionId,
+            path: path ?? null,
+          })
         : await ftpRequest<ListResponse>("list", {
             host, port, username, password, path: path ?? null, protocol, sftpPort,
           });
       setRemoteEntries(response.entries);
       setRemotePath(response.cwd || "/");
     } catch (error) {
-      const message = error instanceof Error ? error.message : String(error);
+      const message = errorMessage(error);
       addLog("error", message);
     }
   };
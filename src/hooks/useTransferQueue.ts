@@ -6,6 +6,8 @@ import type {
   FtpEntry,
   LocalEntry,
   LogEntry,
+  TransferDone,
+  TransferCancelled,
   TransferErrorPayload,
   TransferItem,
   TransferProgress,
@@ -17,6 +19,8 @@ import {
   buildRemotePath,
   createId,
   formatBytes,
+  errorMessage,
+  errorKind,
 } from "../utils";
 import type { ConnectionConfig } from "./useConnection";
 
@@ -78,11 +82,11 @@ export function useTransferQueue({
           const children: { relative_path: string; is_dir: boolean; size: number | null }[] =
             await invoke("list_local_files_recursive", { root: entry.path });
           const baseRemote = buildRemotePath(remotePath || "/", entry.name);
-          try { await invoke("create_dir", { path: baseRemote }); } catch { /* may exist */ }
+          try { await invoke("create_dir", { sessionId: connectionConfig.sessionId, path: baseRemote }); } catch { /* may exist */ }
           for (const child of children) {
             if (child.is_dir) {
               try {
-                await invoke("create_dir", { path: buildRemotePath(baseRemote, child.relative_path) });
+                await invoke("create_dir", { sessionId: connectionConfig.sessionId, path: buildRemotePath(baseRemote, child.relative_path) });
               } catch { /* may exist */ }
             }
           }
@@ -102,7 +106,7 @@ export function useTransferQueue({
             }
           }
         } catch (error) {
-          const message = error instanceof Error ? error.message : String(error);
+          const message = errorMessage(error);
           addLog("error", `Failed to list folder "${entry.name}": ${message}`);
         }
         continue;
@@ -162,7 +166,7 @@ export function useTransferQueue({
         try {
           const fullRemote = buildRemotePath(remotePath || "/", entry.name);
           const children: { relative_path: string; is_dir: boolean; size: number | null }[] =
-            await invoke("list_remote_files_recursive", { path: fullRemote });
+            await invoke("list_remote_files_recursive", { sessionId: connectionConfig.sessionId, path: fullRemote });
           const baseLocal = await join(localPath, entry.name);
           try { await invoke("create_local_dir", { path: baseLocal }); } catch { /* may exist */ }
           for (const child of children) {
@@ -190,7 +194,7 @@ export function useTransferQueue({
             }
           }
         } catch (error) {
-          const message = error instanceof Error ? error.message : String(error);
+          const message = errorMessage(error);
           addLog("error", `Failed to list remote folder "${entry.name}": ${message}`);
         }
         continue;
@@ -263,7 +267,7 @@ export function useTransferQueue({
     };
     try { await send(); }
     catch (error) {
-      const message = error instanceof Error ? error.message : String(error);
+      const message = errorMessage(error);
       if (!shouldFallbackToSftp(message)) throw error;
       await send("sftp");
     }
@@ -300,7 +304,7 @@ export function useTransferQueue({
     };
     try { await send(); }
     catch (error) {
-      const message = error instanceof Error ? error.message : String(error);
+      const message = errorMessage(error);
       if (!shouldFallbackToSftp(message)) throw error;
       await send("sftp");
     }
@@ -330,13 +334,17 @@ export function useTransferQueue({
       const unlistenLog = await listen<LogEntry>("log", (event) => {
         setLogs((prev) => [event.payload, ...prev.slice(0, 199)]);
       });
+      const startupLog = await invoke<LogEntry[]>("take_startup_log").catch(() => [] as LogEntry[]);
+      if (startupLog.length) {
+        setLogs((prev) => [...startupLog.reverse(), ...prev].slice(0, 200));
+      }
       const unlistenProgress = await listen<TransferProgress>("transfer-progress", (event) => {
         progressBuffer.set(event.payload.id, {
           transferred: event.payload.transferred,
           total: event.payload.total,
         });
       });
-      const unlistenComplete = await listen<{ id: string }>("transfer-complete", (event) => {
+      const unlistenComplete = await listen<TransferDone>("transfer-complete", (event) => {
         setQueue((prev) =>
           prev.map((item) =>
             item.id === event.payload.id
@@ -354,7 +362,10 @@ export function useTransferQueue({
           )
         );
       });
-      return () => { unlistenLog(); unlistenProgress(); unlistenComplete(); unlistenError(); };
+      const unlistenCancelled = await listen<TransferCancelled>("transfer-cancelled", (event) => {
+        setQueue((prev) => prev.filter((item) => item.id !== event.payload.id));
+      });
+      return () => { unlistenLog(); unlistenProgress(); unlistenComplete(); unlistenError(); unlistenCancelled(); };
     };
 
     const cleanupPromise = setup();
@@ -380,9 +391,9 @@ export function useTransferQueue({
       try {
         if (isTauri) {
           if (next.direction === "upload") {
-            await invoke("upload_file", { id: next.id, localPath: next.localPath, remotePath: next.remotePath });
+            await invoke("upload_file", { sessionId: connectionConfig.sessionId, id: next.id, localPath: next.localPath, remotePath: next.remotePath });
           } else {
-            await invoke("download_file", { id: next.id, remotePath: next.remotePath, localPath: next.localPath });
+            await invoke("download_file", { sessionId: connectionConfig.sessionId, id: next.id, remotePath: next.remotePath, localPath: next.localPath });
           }
         } else {
           if (next.direction === "upload") {
@@ -399,7 +410,8 @@ export function useTransferQueue({
           );
         }
       } catch (error) {
-        const message = error instanceof Error ? error.message : String(error);
+        if (errorKind(error) === "cancelled") return;
+        const message = errorMessage(error);
         addLog("error", message);
         setQueue((prev) =>
           prev.map((item) =>
@@ -424,6 +436,11 @@ export function useTransferQueue({
 
   const cancelQueuedTransfers = () => {
     setQueue((prev) => prev.filter((item) => item.status !== "queued"));
+    if (!isTauri) return;
+    // Running transfers stop between chunks and leave via transfer-cancelled.
+    activeTransferIds.forEach((id) => {
+      invoke("cancel_transfer", { id }).catch((error) => addLog("error", errorMessage(error)));
+    });
   };
 
   const retryFailedTransfers = () => {
@@ -46,8 +46,14 @@ export type HistoryAction = {
 export type FtpEntry = {
   name: string;
   size?: number | null;
-  modified?: string | null;
+  modified?: number | null;
   is_dir: boolean;
+  is_symlink?: boolean;
+  link_target?: string | null;
+  permissions?: string | null;
+  owner?: string | null;
+  group?: string | null;
+  unique?: string | null;
   raw?: string | null;
 };
 
@@ -57,7 +63,52 @@ export type ListResponse = {
 };
 
 export type ConnectResponse = {
+  session_id?: string;
   cwd: string;
+  local_dir?: string | null;
+};
+
+export type FtpSecurity = "none" | "explicit" | "implicit";
+
+export type CertificateValidation =
+  | { mode: "system" }
+  | { mode: "pinned"; fingerprint: string }
+  | { mode: "accept_once"; fingerprint?: string | null };
+
+export type TransferMode = "passive" | "extended_passive" | "active";
+
+export type PortRange = {
+  start: number;
+  end: number;
+};
+
+export type SiteProfile = {
+  id: string;
+  name: string;
+  folder?: string | null;
+  host: string;
+  port: number;
+  username: string;
+  password_ref?: string | null;
+  protocol: "ftp" | "sftp";
+  security: FtpSecurity;
+  certificate: CertificateValidation;
+  remote_dir?: string | null;
+  local_dir?: string | null;
+  transfer_mode: TransferMode;
+  options: {
+    timeout_secs?: number | null;
+    notes: string;
+    active_ports?: PortRange | null;
+    external_ip?: string | null;
+  };
+};
+
+export type CertificatePrompt = {
+  host: string;
+  port: number;
+  fingerprint: string;
+  reason: string;
 };
 
 export type LocalEntry = {
@@ -85,17 +136,64 @@ export type LogEntry = {
   timestamp: number;
 };
 
-export type TransferProgress = {
+export type TransferRates = {
+  bytes_per_sec: number;
+  average_bytes_per_sec: number;
+  eta_secs?: number | null;
+  elapsed_ms: number;
+};
+
+export type TransferProgress = TransferRates & {
   id: string;
   transferred: number;
   total?: number | null;
 };
 
-export type TransferErrorPayload = {
+export type BatchProgress = TransferRates & {
   id: string;
+  files_done: number;
+  files_total: number;
+  bytes_done: number;
+  bytes_total?: number | null;
+};
+
+export type VerifyMethod = "sha256" | "sha1" | "md5" | "crc32" | "size";
+
+export type TransferDone = {
+  id: string;
+  verified?: VerifyMethod | null;
+};
+
+export type ErrorKind =
+  | "not_connected"
+  | "auth_failed"
+  | "permission_denied"
+  | "not_found"
+  | "timeout"
+  | "tls_error"
+  | "disk_full"
+  | "remote_full"
+  | "cancelled"
+  | "verify_mismatch"
+  | "too_large"
+  | "unsupported"
+  | "other";
+
+export type AppError = {
+  kind: ErrorKind;
+  code?: number | null;
   message: string;
 };
 
+export type TransferCancelled = {
+  id: string;
+  partial_kept: boolean;
+};
+
+export type TransferErrorPayload = AppError & {
+  id: string;
+};
+
 export type TransferStatus = "queued" | "active" | "done" | "error";
 
 export type TransferItem = {
@@ -126,6 +224,9 @@ export type FtpBookmark = {
   host: string;
   port: number;
   username: string;
+  /** Vault reference of the saved password. */
+  passwordRef?: string | null;
+  /** Plaintext password written by older versions; moved to the vault on load. */
   password?: string | null;
 };
 
@@ -159,3 +260,132 @@ export type SortBy =
 export type SortOrder = "asc" | "desc";
 
 export type ThemeMode = "galaxy" | "system" | "light" | "plain-light" | "plain-dark";
+
+export type SyncDirection = "mirror_up" | "mirror_down" | "two_way";
+
+export type SyncAction =
+  | "mkdir_local"
+  | "mkdir_remote"
+  | "upload"
+  | "download"
+  | "delete_local"
+  | "delete_remote"
+  | "conflict";
+
+export type SyncStep = {
+  action: SyncAction;
+  relativePath: string;
+  isDir: boolean;
+  localSize?: number | null;
+  remoteSize?: number | null;
+  localModified?: number | null;
+  remoteModified?: number | null;
+  reason: string;
+};
+
+export type SyncPlan = {
+  localRoot: string;
+  remoteRoot: string;
+  steps: SyncStep[];
+};
+
+export type SyncReport = {
+  completed: number;
+  skipped: number;
+  failed: { relativePath: string; message: string }[];
+  cancelled: boolean;
+};
+
+export type SyncProgress = {
+  id: string;
+  index: number;
+  total: number;
+  relativePath: string;
+  action: SyncAction;
+};
+
+export type SiteImportReport = {
+  sites: SiteProfile[];
+  warnings: string[];
+};
+
+export type VaultStatus = {
+  backend: "keyring" | "file";
+  initialized: boolean;
+  unlocked: boolean;
+  load_error: string | null;
+};
+
+export type WatchInfo = {
+  id: string;
+  local_dir: string;
+  remote_dir: string;
+  session_key: string;
+};
+
+export type ConflictPolicy =
+  | "overwrite"
+  | "skip"
+  | "rename"
+  | "overwrite_if_newer"
+  | "overwrite_if_size_differs"
+  | "ask";
+
+export type TransferDefaults = {
+  concurrency: number;
+  conflictPolicy: ConflictPolicy;
+  timeoutSecs: number;
+  bufferSize: number;
+  defaultLocalDir?: string | null;
+  verify: boolean;
+  keepPartialOnCancel: boolean;
+};
+
+export type RateLimits = {
+  uploadKbps: number;
+  downloadKbps: number;
+};
+
+export type ThrottleSchedule = {
+  days: number[];
+  startMinute: number;
+  endMinute: number;
+  limits: RateLimits;
+};
+
+export type ThrottleConfig = {
+  global: RateLimits;
+  perTransfer: RateLimits;
+  schedules: ThrottleSchedule[];
+};
+
+export type Preferences = {
+  openOnStartup: boolean;
+  closeToTray: boolean;
+  minimizeToTray: boolean;
+  transfers: TransferDefaults;
+  throttle: ThrottleConfig;
+};
+
+export type AutostartStatus = {
+  supported: boolean;
+  enabled: boolean;
+  location?: string | null;
+  command?: string | null;
+  current: boolean;
+};
+
+export type ConflictFileInfo = {
+  path: string;
+  size?: number | null;
+  modified?: number | null;
+};
+
+export type TransferConflict = {
+  id: string;
+  direction: "upload" | "download";
+  source: ConflictFileInfo;
+  target: ConflictFileInfo;
+};
+
+export type ConflictDecision = "overwrite" | "skip" | "rename";
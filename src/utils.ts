@@ -1,6 +1,6 @@
 import type { MouseEvent as ReactMouseEvent, DragEvent as ReactDragEvent } from "react";
 import { open as openExternal } from "@tauri-apps/api/shell";
-import type { DragPayload, Favorite, FtpBookmark, SortBy, SortOrder, ViewMode } from "./types";
+import type { AppError, DragPayload, ErrorKind, Favorite, FtpBookmark, SortBy, SortOrder, ViewMode } from "./types";
 import { isTauri, apiBase } from "./constants";
 
 export const shouldFallbackToSftp = (message: string) =>
@@ -8,6 +8,18 @@ export const shouldFallbackToSftp = (message: string) =>
     message
   );
 
+/** Commands reject with an `AppError`; fetch and JS failures with an `Error`. */
+export const errorMessage = (error: unknown) => {
+  if (error instanceof Error) return error.message;
+  if (error && typeof error === "object" && "message" in error) {
+    return String((error as AppError).message);
+  }
+  return String(error);
+};
+
+export const errorKind = (error: unknown): ErrorKind | null =>
+  error && typeof error === "object" && "kind" in error ? (error as AppError).kind : null;
+
 export const ftpRequest = async <T,>(endpoint: string, body: Record<string, unknown>) => {
   const send = async (payload: Record<string, unknown>) => {
     const response = await fetch(`${apiBase}/api/ftp/${endpoint}`, {
@@ -27,7 +39,7 @@ export const ftpRequest = async <T,>(endpoint: string, body: Record<string, unkn
   try {
     return await send(body);
   } catch (error) {
-    const message = error instanceof Error ? error.message : String(error);
+    const message = errorMessage(error);
     if (!shouldFallbackToSftp(message)) throw error;
     return await send({ ...body, protocol: "sftp", sftpPort: 22 });
   }